@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+
+use des::{net::ObjectPath, time::SimTime};
+use egui::{Align2, Context, FontId, Rect, Sense, Stroke, TopBottomPanel, pos2, vec2};
+
+use crate::{Application, plot::TRACE_COLORS};
+
+/// The number of events kept for the activity view.
+const CAPACITY: usize = 10_000;
+const ROW_HEIGHT: f32 = 14.0;
+const LABEL_WIDTH: f32 = 140.0;
+
+/// The most recent dispatched events and the module that handled them.
+///
+/// Like the event counts, events are attributed via the log watermark,
+/// thus events whose handler did not log are missing.
+#[derive(Debug, Default)]
+pub struct Activity {
+    events: VecDeque<(SimTime, ObjectPath)>,
+}
+
+impl Activity {
+    pub fn record(&mut self, time: SimTime, module: ObjectPath) {
+        if self.events.len() == CAPACITY {
+            self.events.pop_front();
+        }
+        self.events.push_back((time, module));
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// The modules with recorded events, sorted by path.
+    fn modules(&self) -> Vec<&ObjectPath> {
+        let mut modules = self.events.iter().map(|(_, m)| m).collect::<Vec<_>>();
+        modules.sort_by(|l, r| l.as_str().cmp(r.as_str()));
+        modules.dedup();
+        modules
+    }
+
+    fn span(&self) -> (f64, f64) {
+        let start = self.events.front().map_or(0.0, |(t, _)| t.as_secs_f64());
+        let end = self.events.back().map_or(0.0, |(t, _)| t.as_secs_f64());
+        (start, end.max(start + f64::EPSILON))
+    }
+}
+
+impl Application {
+    pub fn render_activity(&mut self, ctx: &Context) {
+        let mut open = None;
+        TopBottomPanel::bottom("activity")
+            .resizable(true)
+            .show(ctx, |ui| {
+                let modules = self.activity.modules();
+                if modules.is_empty() {
+                    ui.weak("No attributed events yet");
+                    return;
+                }
+
+                let (start, end) = self.activity.span();
+                let size = vec2(ui.available_width(), ROW_HEIGHT * modules.len() as f32);
+                let (resp, painter) = ui.allocate_painter(size, Sense::click());
+                let rect = resp.rect;
+                let track = rect.width() - LABEL_WIDTH;
+                let to_x = |t: f64| {
+                    rect.left() + LABEL_WIDTH + ((t - start) / (end - start)) as f32 * track
+                };
+                let row = |i: usize| {
+                    Rect::from_min_size(
+                        pos2(rect.left(), rect.top() + i as f32 * ROW_HEIGHT),
+                        vec2(rect.width(), ROW_HEIGHT),
+                    )
+                };
+
+                for (i, module) in modules.iter().enumerate() {
+                    let row = row(i);
+                    if i % 2 == 1 {
+                        painter.rect_filled(row, 0.0, ui.visuals().faint_bg_color);
+                    }
+                    painter.text(
+                        row.left_center(),
+                        Align2::LEFT_CENTER,
+                        module.as_str(),
+                        FontId::monospace(10.0),
+                        ui.visuals().text_color(),
+                    );
+                }
+
+                for (time, module) in &self.activity.events {
+                    let i = modules.binary_search_by(|m| m.as_str().cmp(module.as_str()));
+                    let Ok(i) = i else { continue };
+                    let row = row(i).shrink2(vec2(0.0, 2.0));
+                    let x = to_x(time.as_secs_f64());
+                    painter.line_segment(
+                        [pos2(x, row.top()), pos2(x, row.bottom())],
+                        Stroke::new(1.0, TRACE_COLORS[i % TRACE_COLORS.len()]),
+                    );
+                }
+
+                let resp = resp.on_hover_text(format!("{start:.6}s to {end:.6}s"));
+                if resp.clicked()
+                    && let Some(pos) = resp.interact_pointer_pos()
+                {
+                    let i = ((pos.y - rect.top()) / ROW_HEIGHT) as usize;
+                    open = modules.get(i).map(|m| (*m).clone());
+                }
+            });
+
+        if let Some(path) = open {
+            self.open_inspector(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded_buffer() {
+        let mut activity = Activity::default();
+        for i in 0..CAPACITY + 10 {
+            let module = ObjectPath::from(if i % 2 == 0 { "b" } else { "a" });
+            activity.record(SimTime::from(i as f64), module);
+        }
+        assert_eq!(activity.events.len(), CAPACITY);
+        assert_eq!(activity.span(), (10.0, (CAPACITY + 9) as f64));
+        assert_eq!(
+            activity.modules(),
+            [&ObjectPath::from("a"), &ObjectPath::from("b")]
+        );
+    }
+}
@@ -1,41 +1,254 @@
-use std::ops::ControlFlow;
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufWriter, Write},
+    ops::ControlFlow,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
-use des::net::ObjectPath;
-use egui::{ComboBox, Context, RichText, ScrollArea, SidePanel};
+use des::{net::ObjectPath, time::SimTime};
+use egui::{
+    Align, CollapsingHeader, Color32, ComboBox, Context, DragValue, RichText, ScrollArea,
+    SidePanel, Stroke, StrokeKind, TextEdit,
+};
+use evalexpr::{ContextWithMutableVariables, EvalexprError, HashMapContext, Node};
 use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
 use serde_norway::Value;
 
 use crate::{
-    Application,
+    Application, BreakpointReq,
     inspector::{Ctx, display},
     plot::access,
 };
 
 #[derive(Debug)]
 pub struct Breakpoint {
+    /// Unique among all breakpoints, since a key may have several.
+    pub id: usize,
     pub path: ObjectPath,
     pub key: String,
     pub kind: BreakpointKind,
     pub last: Option<Value>,
     pub triggered: bool,
     pub remove: bool,
+    /// The observed value each time the breakpoint triggered.
+    pub history: VecDeque<(SimTime, Value)>,
+    /// How often the condition was met since the last reset, including
+    /// ignored hits.
+    pub hits: usize,
+    /// The number of initial hits that do not break.
+    pub ignore: usize,
+    /// Disables the breakpoint once it has this many hits that were not
+    /// ignored. A reset re-arms it.
+    pub disable_after: Option<usize>,
+    /// The kind before "Disable All", restored by "Enable All".
+    pub disabled_kind: Option<BreakpointKind>,
+    // The compiled expression of a `Condition` and its source.
+    compiled: Option<(String, Result<Node, EvalexprError>)>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+const HISTORY_LIMIT: usize = 256;
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum BreakpointKind {
     Disabled,
     OnValueChanged,
     OnValueAppeared,
     OnValueDisappeared,
+    /// Triggers when a numeric value crosses above the threshold.
+    OnValueAbove(f64),
+    /// Triggers when a numeric value crosses below the threshold.
+    OnValueBelow(f64),
+    /// Triggers when an expression over the numeric `value`, e.g.
+    /// `value > 10 && value != 15`, becomes true.
+    Condition(String),
+    /// Triggers when the variant of an enum changes, regardless of the
+    /// data it holds, e.g. for state machines.
+    OnVariantChanged,
+}
+
+impl BreakpointKind {
+    fn threshold(&self) -> Option<f64> {
+        match self {
+            Self::OnValueAbove(t) | Self::OnValueBelow(t) => Some(*t),
+            _ => None,
+        }
+    }
+}
+
+/// The variant of a serialized enum. Variants with data are tagged, unit
+/// variants are plain strings.
+fn variant(value: &Value) -> Option<String> {
+    match value {
+        Value::Tagged(tagged) => Some(tagged.tag.to_string().trim_start_matches('!').to_string()),
+        Value::String(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// Whether a breakpoint of `kind` on `key` exists, a second one would only
+/// trigger twice.
+pub fn exists(
+    breakpoints: &[Breakpoint],
+    path: &ObjectPath,
+    key: &str,
+    kind: &BreakpointKind,
+) -> bool {
+    breakpoints
+        .iter()
+        .any(|b| b.path == *path && b.key == key && b.kind == *kind)
+}
+
+/// Adds a breakpoint requested from an inspector, unless it exists.
+pub fn request(breakpoints: &mut Vec<Breakpoint>, (path, key, value): BreakpointReq) {
+    // Enums mostly matter by their variant
+    let kind = match value {
+        Some(Value::Tagged(_)) => BreakpointKind::OnVariantChanged,
+        _ => BreakpointKind::OnValueChanged,
+    };
+    if exists(breakpoints, &path, &key, &kind) {
+        ::tracing::warn!("a {kind:?} breakpoint on {path} {key} already exists");
+    } else {
+        breakpoints.push(Breakpoint::new(path, key, kind, value));
+    }
 }
 
 impl Breakpoint {
+    pub fn new(path: ObjectPath, key: String, kind: BreakpointKind, last: Option<Value>) -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            path,
+            key,
+            kind,
+            last,
+            triggered: false,
+            remove: false,
+            history: VecDeque::new(),
+            hits: 0,
+            ignore: 0,
+            disable_after: None,
+            disabled_kind: None,
+            compiled: None,
+        }
+    }
+
     pub fn update(&mut self, observers: &FxHashMap<ObjectPath, Value>) -> ControlFlow<()> {
         self.triggered = false;
-        self.update_inner(observers).map_break(|b| {
-            self.triggered = true;
-            b
-        })
+        if self.update_inner(observers).is_continue() {
+            return ControlFlow::Continue(());
+        }
+
+        self.hits += 1;
+        if self.hits <= self.ignore {
+            return ControlFlow::Continue(());
+        }
+        if self
+            .disable_after
+            .is_some_and(|n| self.hits - self.ignore >= n)
+        {
+            self.disable();
+        }
+
+        self.triggered = true;
+        if self.history.len() >= HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+        self.history
+            .push_back((SimTime::now(), self.last.clone().unwrap_or(Value::Null)));
+        ControlFlow::Break(())
+    }
+
+    /// Disables the breakpoint, remembering the current kind.
+    pub fn disable(&mut self) {
+        if self.kind != BreakpointKind::Disabled {
+            self.disabled_kind = Some(std::mem::replace(&mut self.kind, BreakpointKind::Disabled));
+        }
+    }
+
+    /// Restores the kind from before `disable`, unless it was changed since.
+    pub fn enable(&mut self) {
+        if let Some(kind) = self.disabled_kind.take()
+            && self.kind == BreakpointKind::Disabled
+        {
+            self.kind = kind;
+        }
+    }
+
+    /// Writes the trigger history to `<path>.<key>.<id>.breakpoint.yaml` in
+    /// `dir`, since a key may have several breakpoints.
+    pub fn export_history(&self, dir: &Path) -> io::Result<()> {
+        let file = dir.join(format!(
+            "{}.{}.{}.breakpoint.yaml",
+            self.path, self.key, self.id
+        ));
+        let mut f = BufWriter::new(File::create(file)?);
+        serde_norway::to_writer(&mut f, &self.history).map_err(io::Error::other)?;
+        f.flush()
+    }
+
+    /// Resumes from the current observed value, e.g. after a reset.
+    pub fn reset(&mut self, observers: &FxHashMap<ObjectPath, Value>) {
+        self.last = observers
+            .get(&self.path)
+            .and_then(|value| access(value, &self.key));
+        self.triggered = false;
+        // Re-arm a breakpoint that disabled itself after `disable_after` hits
+        if self
+            .disable_after
+            .is_some_and(|n| self.hits.saturating_sub(self.ignore) >= n)
+        {
+            self.enable();
+        }
+        self.hits = 0;
+
+        let now = SimTime::now();
+        self.history.retain(|(time, _)| *time <= now);
+    }
+
+    /// The compiled condition, recompiled only when the expression changed.
+    fn condition(&mut self) -> Option<&Result<Node, EvalexprError>> {
+        let BreakpointKind::Condition(expr) = &self.kind else {
+            return None;
+        };
+        if self
+            .compiled
+            .as_ref()
+            .is_none_or(|(source, _)| source != expr)
+        {
+            self.compiled = Some((expr.clone(), evalexpr::build_operator_tree(expr)));
+        }
+        self.compiled.as_ref().map(|(_, node)| node)
+    }
+
+    /// Why the condition can not be evaluated, if it is invalid.
+    pub fn condition_error(&mut self) -> Option<String> {
+        self.condition()?.as_ref().err().map(ToString::to_string)
+    }
+
+    /// Evaluates the condition for `value`. Invalid expressions and
+    /// non-numeric values never hold.
+    fn eval_condition(&mut self, value: Option<&Value>) -> bool {
+        // Integers must stay integers, evalexpr never equates Int and Float
+        let number = if let Some(n) = value.and_then(Value::as_i64) {
+            evalexpr::Value::Int(n)
+        } else if let Some(n) = value.and_then(Value::as_f64) {
+            evalexpr::Value::Float(n)
+        } else {
+            return false;
+        };
+        let Some(Ok(node)) = self.condition() else {
+            return false;
+        };
+
+        let mut context = HashMapContext::new();
+        context
+            .set_value("value".to_string(), number)
+            .expect("context is mutable");
+        node.eval_boolean_with_context(&context).unwrap_or(false)
     }
 
     fn update_inner(&mut self, observers: &FxHashMap<ObjectPath, Value>) -> ControlFlow<()> {
@@ -43,7 +256,28 @@ impl Breakpoint {
             .get(&self.path)
             .and_then(|value| access(value, &self.key));
 
+        let number = |v: &Option<Value>| v.as_ref().and_then(Value::as_f64);
+        // `last` holds the previous value, so comparing it yields the
+        // previous comparison result. Only a crossing triggers.
+        let crossed = |cmp: fn(f64, f64) -> bool, t: f64| {
+            let was = number(&self.last).is_some_and(|v| cmp(v, t));
+            let is = number(&value).is_some_and(|v| cmp(v, t));
+            match !was && is {
+                true => ControlFlow::Break(()),
+                false => ControlFlow::Continue(()),
+            }
+        };
+
         let ret = match self.kind {
+            BreakpointKind::Condition(_) => {
+                let last = self.last.take();
+                let was = self.eval_condition(last.as_ref());
+                let is = self.eval_condition(value.as_ref());
+                match !was && is {
+                    true => ControlFlow::Break(()),
+                    false => ControlFlow::Continue(()),
+                }
+            }
             BreakpointKind::Disabled => ControlFlow::Continue(()),
             BreakpointKind::OnValueChanged => (self.last == value)
                 .then_some(ControlFlow::Continue(()))
@@ -54,6 +288,14 @@ impl Breakpoint {
             BreakpointKind::OnValueDisappeared => (self.last.is_some() && value.is_none())
                 .then_some(ControlFlow::Break(()))
                 .unwrap_or(ControlFlow::Continue(())),
+            BreakpointKind::OnValueAbove(t) => crossed(|v, t| v > t, t),
+            BreakpointKind::OnValueBelow(t) => crossed(|v, t| v < t, t),
+            BreakpointKind::OnVariantChanged => {
+                match self.last.as_ref().and_then(variant) == value.as_ref().and_then(variant) {
+                    true => ControlFlow::Continue(()),
+                    false => ControlFlow::Break(()),
+                }
+            }
         };
         self.last = value;
         ret
@@ -68,17 +310,41 @@ impl Application {
 
         SidePanel::left("breakpoint-panel").show(ctx, |ui| {
             ui.label(RichText::new("Breakpoints").strong());
+            ui.horizontal(|ui| {
+                if ui.button("Disable All").clicked() {
+                    self.breakpoints.iter_mut().for_each(Breakpoint::disable);
+                }
+                if ui.button("Enable All").clicked() {
+                    self.breakpoints.iter_mut().for_each(Breakpoint::enable);
+                }
+                // Unused observers are dropped at the end of the frame
+                if ui.button("Clear All").clicked() {
+                    self.breakpoints.clear();
+                    self.hit = None;
+                }
+            });
             ui.separator();
 
             ScrollArea::vertical().show(ui, |ui| {
-                for b in &mut self.breakpoints {
-                    ui.horizontal(|ui| {
+                for (i, b) in self.breakpoints.iter_mut().enumerate() {
+                    let row = ui.horizontal(|ui| {
                         let bid = format!("{}", b.path);
-                        ui.label(match b.triggered {
-                            true => RichText::new(&bid).strong(),
-                            false => RichText::new(&bid),
+                        if let Some(e) = b.condition_error() {
+                            ui.label(RichText::new(&bid).color(Color32::RED))
+                                .on_hover_text(format!("Invalid condition: {e}"));
+                        } else if self.observe.is_stale(&b.path) {
+                            ui.label(RichText::new(format!("⚠ {bid}")).weak())
+                                .on_hover_text("The observed path no longer exists");
+                        } else {
+                            ui.label(match b.triggered {
+                                true => RichText::new(&bid).strong(),
+                                false => RichText::new(&bid),
+                            });
+                        }
+                        let threshold = b.kind.threshold().unwrap_or_else(|| {
+                            b.last.as_ref().and_then(Value::as_f64).unwrap_or(0.0)
                         });
-                        ComboBox::new((&b.path, &b.key), "")
+                        ComboBox::new(("breakpoint-kind", b.id), "")
                             .selected_text(format!("{:?}", b.kind))
                             .show_ui(ui, |ui| {
                                 ui.selectable_value(
@@ -101,7 +367,37 @@ impl Application {
                                     BreakpointKind::OnValueDisappeared,
                                     "OnValueDisappeared",
                                 );
+                                ui.selectable_value(
+                                    &mut b.kind,
+                                    BreakpointKind::OnValueAbove(threshold),
+                                    "OnValueAbove",
+                                );
+                                ui.selectable_value(
+                                    &mut b.kind,
+                                    BreakpointKind::OnValueBelow(threshold),
+                                    "OnValueBelow",
+                                );
+                                ui.selectable_value(
+                                    &mut b.kind,
+                                    BreakpointKind::OnVariantChanged,
+                                    "OnVariantChanged",
+                                );
+                                if !matches!(b.kind, BreakpointKind::Condition(_)) {
+                                    ui.selectable_value(
+                                        &mut b.kind,
+                                        BreakpointKind::Condition(format!("value > {threshold}")),
+                                        "Condition",
+                                    );
+                                }
                             });
+                        if let BreakpointKind::Condition(expr) = &mut b.kind {
+                            ui.add(TextEdit::singleline(expr).desired_width(160.0));
+                        }
+                        if let BreakpointKind::OnValueAbove(t) | BreakpointKind::OnValueBelow(t) =
+                            &mut b.kind
+                        {
+                            ui.add(DragValue::new(t).speed(0.1));
+                        }
 
                         // body
                         if let Some(ref last) = b.last {
@@ -112,6 +408,10 @@ impl Application {
                                 Ctx {
                                     node: &b.path,
                                     actions: None,
+                                    nav: None,
+                                    changed: None,
+                                    sparks: None,
+                                    breakpoints: None,
                                 },
                                 last,
                                 b.key.clone(),
@@ -120,13 +420,243 @@ impl Application {
                             ui.label(&b.key);
                         }
 
+                        ui.label(format!("{} hits", b.hits));
+                        ui.add(DragValue::new(&mut b.ignore).prefix("ignore "))
+                            .on_hover_text("Do not break on the first n hits");
+                        let mut limited = b.disable_after.is_some();
+                        ui.checkbox(&mut limited, "disable after");
+                        match limited {
+                            true => {
+                                ui.add(
+                                    DragValue::new(b.disable_after.get_or_insert(1))
+                                        .range(1..=usize::MAX),
+                                );
+                            }
+                            false => b.disable_after = None,
+                        }
+
                         // remove
                         if ui.button("Remove").clicked() {
                             b.remove = true;
                         }
                     });
+                    if self.hit == Some(i) {
+                        ui.painter().rect_stroke(
+                            row.response.rect.expand(2.0),
+                            2.0,
+                            Stroke::new(1.0, Color32::YELLOW),
+                            StrokeKind::Outside,
+                        );
+                        if std::mem::take(&mut self.scroll_to_hit) {
+                            row.response.scroll_to_me(Some(Align::Center));
+                        }
+                    }
+
+                    if !b.history.is_empty() {
+                        CollapsingHeader::new(format!("History ({})", b.history.len()))
+                            .id_salt(("breakpoint-history", b.id))
+                            .show(ui, |ui| {
+                                if ui.button("Export").clicked()
+                                    && let Err(e) = b.export_history(&self.dir)
+                                {
+                                    ::tracing::error!("failed to export breakpoint history: {e}");
+                                }
+                                for (time, value) in b.history.iter().rev() {
+                                    ui.horizontal(|ui| {
+                                        ui.label(format!("{time:?}"));
+                                        display(
+                                            ui,
+                                            Ctx {
+                                                node: &b.path,
+                                                actions: None,
+                                                nav: None,
+                                                changed: None,
+                                                sparks: None,
+                                                breakpoints: None,
+                                            },
+                                            value,
+                                            b.key.clone(),
+                                        );
+                                    });
+                                }
+                            });
+                    }
                 }
             });
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plot::observers;
+
+    #[test]
+    fn threshold_triggers_on_crossing() {
+        let path = ObjectPath::from("ping");
+        let mut b = Breakpoint::new(
+            path.clone(),
+            "counter".to_string(),
+            BreakpointKind::OnValueAbove(2.0),
+            Some(Value::from(0)),
+        );
+
+        let mut flows = Vec::new();
+        for v in [1, 3, 4, 1, 5] {
+            flows.push(b.update(&observers(&path, "counter", v)).is_break());
+        }
+        assert_eq!(flows, [false, true, false, false, true]);
+    }
+
+    #[test]
+    fn condition_triggers_when_it_becomes_true() {
+        let path = ObjectPath::from("ping");
+        let mut b = Breakpoint::new(
+            path.clone(),
+            "counter".to_string(),
+            BreakpointKind::Condition("value > 10 && value != 15".to_string()),
+            Some(Value::from(0)),
+        );
+
+        let mut flows = Vec::new();
+        for v in [5, 11, 12, 15, 16] {
+            flows.push(b.update(&observers(&path, "counter", v)).is_break());
+        }
+        assert_eq!(flows, [false, true, false, false, true]);
+
+        b.kind = BreakpointKind::Condition("value == 2.5".to_string());
+        b.last = Some(Value::from(0));
+        let mut flows = Vec::new();
+        for v in [1.5, 2.5, 3.5] {
+            flows.push(b.update(&observers(&path, "counter", v)).is_break());
+        }
+        assert_eq!(flows, [false, true, false]);
+
+        b.kind = BreakpointKind::Condition("value >".to_string());
+        assert!(b.condition_error().is_some());
+    }
+
+    #[test]
+    fn variant_triggers_on_tag_change() {
+        let path = ObjectPath::from("ping");
+        let mut b = Breakpoint::new(
+            path.clone(),
+            "state".to_string(),
+            BreakpointKind::OnVariantChanged,
+            Some(Value::from("Idle")),
+        );
+
+        let mut flows = Vec::new();
+        for state in ["Idle", "!Sending 1", "!Sending 2", "!Waiting 2", "Idle"] {
+            flows.push(b.update(&observers(&path, "state", state)).is_break());
+        }
+        assert_eq!(flows, [false, true, false, true, true]);
+    }
+
+    #[test]
+    fn shares_key_with_distinct_ids() {
+        let path = ObjectPath::from("ping");
+        let above = Breakpoint::new(
+            path.clone(),
+            "counter".to_string(),
+            BreakpointKind::OnValueAbove(10.0),
+            None,
+        );
+        let below = Breakpoint::new(
+            path,
+            "counter".to_string(),
+            BreakpointKind::OnValueBelow(5.0),
+            None,
+        );
+        assert_ne!(above.id, below.id);
+    }
+
+    #[test]
+    fn repeated_request_is_added_once() {
+        let path = ObjectPath::from("ping");
+        let mut breakpoints = Vec::new();
+        for _ in 0..2 {
+            request(
+                &mut breakpoints,
+                (path.clone(), "state".to_string(), Some(Value::from("Idle"))),
+            );
+        }
+        assert_eq!(breakpoints.len(), 1);
+        assert_eq!(breakpoints[0].kind, BreakpointKind::OnValueChanged);
+
+        let state = serde_norway::from_str("!Sending 1").unwrap();
+        request(&mut breakpoints, (path, "state".to_string(), Some(state)));
+        assert_eq!(breakpoints.len(), 2);
+        assert_eq!(breakpoints[1].kind, BreakpointKind::OnVariantChanged);
+    }
+
+    #[test]
+    fn other_kinds_on_the_same_key_are_not_duplicates() {
+        let path = ObjectPath::from("ping");
+        let breakpoints = [Breakpoint::new(
+            path.clone(),
+            "counter".to_string(),
+            BreakpointKind::OnValueAbove(10.0),
+            None,
+        )];
+        assert!(exists(
+            &breakpoints,
+            &path,
+            "counter",
+            &BreakpointKind::OnValueAbove(10.0)
+        ));
+        assert!(!exists(
+            &breakpoints,
+            &path,
+            "counter",
+            &BreakpointKind::OnValueBelow(5.0)
+        ));
+        assert!(!exists(
+            &breakpoints,
+            &path,
+            "other",
+            &BreakpointKind::OnValueAbove(10.0)
+        ));
+    }
+
+    #[test]
+    fn ignores_first_hits() {
+        let path = ObjectPath::from("ping");
+        let mut b = Breakpoint::new(
+            path.clone(),
+            "counter".to_string(),
+            BreakpointKind::OnValueChanged,
+            Some(Value::from(0)),
+        );
+        b.ignore = 2;
+        b.disable_after = Some(4);
+
+        let mut flows = Vec::new();
+        for v in 1..=6 {
+            flows.push(b.update(&observers(&path, "counter", v)).is_break());
+        }
+        assert_eq!(flows, [false, false, true, true, true, true]);
+        assert_eq!(b.hits, 6);
+        assert_eq!(b.kind, BreakpointKind::Disabled);
+
+        b.reset(&observers(&path, "counter", 0));
+        assert_eq!(b.kind, BreakpointKind::OnValueChanged);
+        assert_eq!(b.hits, 0);
+    }
+
+    #[test]
+    fn enable_restores_kind() {
+        let mut b = Breakpoint::new(
+            ObjectPath::from("ping"),
+            "counter".to_string(),
+            BreakpointKind::OnValueAbove(3.0),
+            None,
+        );
+        b.disable();
+        b.disable();
+        assert_eq!(b.kind, BreakpointKind::Disabled);
+        b.enable();
+        assert_eq!(b.kind, BreakpointKind::OnValueAbove(3.0));
+    }
+}
@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use des::prelude::*;
+use serde_norway::{Mapping, Value};
+
+/// Prefix of the pseudo-paths under which channels are observed,
+/// e.g. `channel/ping.port->pong.port`.
+pub const CHANNEL_PREFIX: &str = "channel/";
+
+pub fn channel_path(source: &GateRef, target: &GateRef) -> ObjectPath {
+    ObjectPath::from(format!(
+        "{CHANNEL_PREFIX}{}->{}",
+        source.path(),
+        target.path()
+    ))
+}
+
+pub fn is_channel_path(path: &ObjectPath) -> bool {
+    path.as_str().starts_with(CHANNEL_PREFIX)
+}
+
+/// A gate of a module, as listed in its inspector.
+#[derive(Debug, Clone)]
+pub struct GateInfo {
+    pub name: String,
+    pub peer: ObjectPath,
+    // Whether the gate receives from `peer`, instead of sending to it
+    pub incoming: bool,
+    pub channel: Option<(ObjectPath, Mapping)>,
+}
+
+/// Live statistics of a single channel, accumulated while stepping.
+///
+/// des exposes neither the queue of a channel nor the messages it
+/// dropped, thus only transmissions are counted. Each transmission moves
+/// the finish time, which is how they are detected. Transmissions that
+/// start and finish between two updates, e.g. when sampling, are missed.
+#[derive(Debug, Default)]
+pub struct ChannelStats {
+    busy: Duration,
+    busy_since: Option<SimTime>,
+    transmissions: u64,
+    last_finish: Option<SimTime>,
+}
+
+impl ChannelStats {
+    fn update(&mut self, channel: &ChannelRef) {
+        let now = SimTime::now();
+        let finish = channel.transmission_finish_time();
+        if self.last_finish != Some(finish) && finish > SimTime::ZERO {
+            self.transmissions += 1;
+        }
+        self.last_finish = Some(finish);
+
+        match (channel.is_busy(), self.busy_since) {
+            (true, None) => self.busy_since = Some(now),
+            (false, Some(since)) => {
+                self.busy += now - since;
+                self.busy_since = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn utilization(&self) -> f64 {
+        let now = SimTime::now();
+        let busy = self.busy + self.busy_since.map_or(Duration::ZERO, |since| now - since);
+        if now.as_secs_f64() > 0.0 {
+            busy.as_secs_f64() / now.as_secs_f64()
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Observer source for all channels of the topology.
+///
+/// Channels are enumerated once from the edges of `sim.topology()`, since
+/// the topology is frozen once the runtime is built. Stats are tracked
+/// for every channel, regardless of whether it is observed, so that
+/// utilization is correct once an inspector is opened.
+#[derive(Debug, Default)]
+pub struct ChannelObserver {
+    channels: Option<Vec<(ObjectPath, ChannelRef, ChannelStats)>>,
+}
+
+impl ChannelObserver {
+    fn channels(&mut self, sim: &Sim<()>) -> &mut Vec<(ObjectPath, ChannelRef, ChannelStats)> {
+        self.channels.get_or_insert_with(|| {
+            let mut channels = sim
+                .topology()
+                .edge_weights()
+                .filter_map(|edge| {
+                    let channel = edge.source.channel()?;
+                    Some((
+                        channel_path(&edge.source, &edge.target),
+                        channel,
+                        ChannelStats::default(),
+                    ))
+                })
+                .collect::<Vec<_>>();
+            channels.sort_by(|l, r| l.0.as_str().cmp(r.0.as_str()));
+            channels.dedup_by(|l, r| l.0 == r.0);
+            channels
+        })
+    }
+
+    pub fn paths(&mut self, sim: &Sim<()>) -> Vec<ObjectPath> {
+        self.channels(sim)
+            .iter()
+            .map(|(path, _, _)| path.clone())
+            .collect()
+    }
+
+    pub fn update(&mut self, sim: &Sim<()>) {
+        for (_, channel, stats) in self.channels(sim) {
+            stats.update(channel);
+        }
+    }
+
+    /// The connected gates of `module`, with the metrics of their channel if
+    /// the connection has one. Outgoing gates come first.
+    ///
+    /// des has no API to peek at messages, that are scheduled but not yet
+    /// delivered, so incoming gates only report the transmission currently
+    /// in flight on their channel.
+    pub fn gates(&mut self, sim: &Sim<()>, module: &ObjectPath) -> Vec<GateInfo> {
+        let mut gates = sim
+            .topology()
+            .edge_weights()
+            .filter_map(|edge| {
+                let (gate, peer, incoming) = if edge.source.owner().path() == *module {
+                    (&edge.source, &edge.target, false)
+                } else if edge.target.owner().path() == *module {
+                    (&edge.target, &edge.source, true)
+                } else {
+                    return None;
+                };
+                let path = channel_path(&edge.source, &edge.target);
+                Some(GateInfo {
+                    name: gate.name().to_string(),
+                    peer: peer.path(),
+                    incoming,
+                    channel: self.load(sim, &path).map(|metrics| (path, metrics)),
+                })
+            })
+            .collect::<Vec<_>>();
+        gates.sort_by(|l, r| (l.incoming, &l.name).cmp(&(r.incoming, &r.name)));
+        gates
+    }
+
+    pub fn load(&mut self, sim: &Sim<()>, path: &ObjectPath) -> Option<Mapping> {
+        let (_, channel, stats) = self.channels(sim).iter().find(|(p, _, _)| p == path)?;
+        Some(load_channel_value(channel, stats))
+    }
+}
+
+fn load_channel_value(channel: &ChannelRef, stats: &ChannelStats) -> Mapping {
+    let metrics = channel.metrics();
+    let now = SimTime::now();
+    let finish = channel.transmission_finish_time();
+    let in_flight = if finish > now {
+        (finish - now).as_secs_f64() * metrics.bitrate as f64 / 8.0
+    } else {
+        0.0
+    };
+
+    Mapping::from_iter([
+        (Value::from("bitrate"), Value::from(metrics.bitrate as u64)),
+        (
+            Value::from("latency"),
+            Value::from(metrics.latency.as_secs_f64()),
+        ),
+        (
+            Value::from("jitter"),
+            Value::from(metrics.jitter.as_secs_f64()),
+        ),
+        (
+            Value::from("drop_behaviour"),
+            Value::String(format!("{:?}", metrics.drop_behaviour)),
+        ),
+        (Value::from("busy"), Value::Bool(channel.is_busy())),
+        (Value::from("utilization"), Value::from(stats.utilization())),
+        (
+            Value::from("transmissions"),
+            Value::from(stats.transmissions),
+        ),
+        (
+            Value::from("bytes_in_flight"),
+            Value::from(in_flight.round()),
+        ),
+        (
+            Value::from("transmission_finish"),
+            Value::from(finish.as_secs_f64()),
+        ),
+    ])
+}
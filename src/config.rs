@@ -0,0 +1,206 @@
+use std::{fs::File, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Combine, Op, breakpoint::BreakpointKind, tracing::LogColors};
+
+/// A shareable debugging setup, loaded from YAML.
+///
+/// ```yaml
+/// scenario: ping-pong
+/// seed: 42
+/// mode: Paused
+/// events_per_frame: 100
+/// inspectors: [ping]
+/// breakpoints:
+///   - { path: ping, key: counter, kind: OnValueChanged }
+/// traces:
+///   - { path: pong, key: counter, max_points: 10000 }
+///   - { path: pong, key: latency, vs: { path: pong, key: queue } }
+///   - { path: "net.*", key: queue_len, combine: Sum }
+///   - { path: net, key: sent, derive: { op: Sub, path: net, key: received } }
+/// layout: { modules: true, breakpoints: true, graph: false, errors: false }
+/// window: { title: ping-pong, size: [1280, 720], maximized: false }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GuiConfig {
+    /// The name of a registered scenario, see `sim::SCENARIOS`.
+    pub scenario: Option<String>,
+    pub seed: Option<u64>,
+    pub mode: ExecutionMode,
+    pub events_per_frame: Option<usize>,
+    pub inspectors: Vec<String>,
+    pub breakpoints: Vec<BreakpointConfig>,
+    pub traces: Vec<TraceConfig>,
+    pub layout: LayoutConfig,
+    pub window: WindowConfig,
+    pub log_colors: LogColors,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ExecutionMode {
+    #[default]
+    Paused,
+    Running,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BreakpointConfig {
+    pub path: String,
+    pub key: String,
+    #[serde(default = "default_breakpoint_kind")]
+    pub kind: BreakpointKind,
+}
+
+fn default_breakpoint_kind() -> BreakpointKind {
+    BreakpointKind::OnValueChanged
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TraceConfig {
+    pub path: String,
+    pub key: String,
+    /// The x axis of a scatter plot, instead of the sim time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vs: Option<Box<TraceConfig>>,
+    /// Plots the elements of a numeric sequence as bars.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sequence: bool,
+    /// Drops the oldest points beyond this count, to bound memory on long runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_points: Option<usize>,
+    /// Combines the key of all modules matching `path`, in which `*` is a
+    /// wildcard.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub combine: Option<Combine>,
+    /// Combines the key with another one, e.g. their difference.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derive: Option<DeriveConfig>,
+    /// Draws a rolling mean over this many points next to the trace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smooth: Option<usize>,
+}
+
+/// The second operand of a derived trace, see `TraceConfig::derive`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DeriveConfig {
+    pub op: Op,
+    pub path: String,
+    pub key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LayoutConfig {
+    pub modules: bool,
+    pub breakpoints: bool,
+    pub graph: bool,
+    pub errors: bool,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            modules: true,
+            breakpoints: false,
+            graph: false,
+            errors: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub title: String,
+    /// Initial inner size in points, only relevant if not maximized.
+    pub size: Option<[f32; 2]>,
+    pub maximized: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: "des-gui".to_string(),
+            size: None,
+            maximized: true,
+        }
+    }
+}
+
+impl GuiConfig {
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let f = File::open(path)?;
+        serde_norway::from_reader(f).map_err(io::Error::other)
+    }
+
+    /// Adds a breakpoint on `key` of the module at `path`.
+    pub fn with_breakpoint(
+        mut self,
+        path: impl Into<String>,
+        key: impl Into<String>,
+        kind: BreakpointKind,
+    ) -> Self {
+        self.breakpoints.push(BreakpointConfig {
+            path: path.into(),
+            key: key.into(),
+            kind,
+        });
+        self
+    }
+
+    /// Adds a trace of `key` of the module at `path` over the sim time.
+    pub fn with_trace(mut self, path: impl Into<String>, key: impl Into<String>) -> Self {
+        self.traces.push(TraceConfig {
+            path: path.into(),
+            key: key.into(),
+            vs: None,
+            sequence: false,
+            max_points: None,
+            combine: None,
+            derive: None,
+            smooth: None,
+        });
+        self
+    }
+
+    /// Opens an inspector for the module at `path`.
+    pub fn with_inspector(mut self, path: impl Into<String>) -> Self {
+        self.inspectors.push(path.into());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_partial_config() {
+        let config: GuiConfig =
+            serde_norway::from_str("seed: 42\nbreakpoints:\n  - { path: ping, key: counter }\n")
+                .unwrap();
+
+        assert_eq!(config.seed, Some(42));
+        assert_eq!(config.mode, ExecutionMode::Paused);
+        assert_eq!(config.breakpoints.len(), 1);
+        assert_eq!(config.breakpoints[0].kind, BreakpointKind::OnValueChanged);
+        assert!(config.layout.modules);
+        assert!(config.traces.is_empty());
+        assert_eq!(config.window.title, "des-gui");
+        assert!(config.window.maximized);
+    }
+
+    #[test]
+    fn build_config() {
+        let config = GuiConfig::default()
+            .with_breakpoint("ping", "counter", BreakpointKind::OnValueChanged)
+            .with_trace("pong", "counter")
+            .with_inspector("ping");
+
+        assert_eq!(config.breakpoints[0].path, "ping");
+        assert_eq!(config.traces[0].key, "counter");
+        assert_eq!(config.inspectors, ["ping"]);
+    }
+}
@@ -1,14 +1,86 @@
-use egui::{Align, Color32, Context, Layout, RichText, Slider};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
 
-use crate::{Application, Rt};
+use des::time::SimTime;
+use egui::{
+    Align, Color32, ComboBox, Context, DragValue, Key, Layout, ProgressBar, RichText, Slider,
+    TextEdit, TopBottomPanel, Ui,
+};
+use egui_file_dialog::FileDialog;
+use tracing::Level;
+
+use crate::{
+    Application, Baseline, BreakpointKind, DEFAULT_FPS, Rt, RunState, THROTTLE_WARNING,
+    tracing::{DEFAULT_MAX_EVENTS, DEFAULT_MAX_MERGED, LogColors, LogFormat},
+};
+
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(1);
+
+/// Events dispatched per second, averaged over the last `THROUGHPUT_WINDOW`.
+#[derive(Debug, Default)]
+pub struct Throughput {
+    samples: VecDeque<(Instant, usize)>,
+}
+
+impl Throughput {
+    pub fn record(&mut self, now: Instant, events: usize) {
+        // A reset or rewind restarts the count
+        if self.samples.back().is_some_and(|(_, e)| *e > events) {
+            self.samples.clear();
+        }
+        self.samples.push_back((now, events));
+        while self
+            .samples
+            .get(1)
+            .is_some_and(|(t, _)| now.duration_since(*t) >= THROUGHPUT_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn per_second(&self) -> f64 {
+        let (Some((t0, e0)), Some((t1, e1))) = (self.samples.front(), self.samples.back()) else {
+            return 0.0;
+        };
+        let elapsed = t1.duration_since(*t0).as_secs_f64();
+        if elapsed > 0.0 {
+            (e1 - e0) as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+}
 
 impl Application {
     pub fn render_controls(&mut self, ctx: &Context) {
         let (time, itr, _, has_err) = match &self.rt {
             Rt::Runtime(r) => (r.sim_time(), r.num_events_dispatched(), &r.app, false),
             Rt::Finished(r) => (r.time, r.profiler.event_count, &r.app, r.error.is_some()),
+            Rt::Finishing => unreachable!("the runtime is restored within Rt::finish"),
         };
 
+        let finished = matches!(self.rt, Rt::Finished(_));
+        self.handle_shortcuts(ctx);
+        self.throughput.record(Instant::now(), itr);
+
+        self.logs_dialog.update(ctx);
+        if let Some(path) = self.logs_dialog.take_picked()
+            && let Err(e) = self.logs.save(&path, self.logs_format)
+        {
+            ::tracing::error!("failed to save logs to {}: {e}", path.display());
+        }
+        self.update_recorder(ctx);
+        self.dump_dialog.update(ctx);
+        if let Some(path) = self.dump_dialog.take_picked()
+            && let Some((value, format)) = self.dump.take()
+            && let Err(e) = format.save(&path, &value)
+        {
+            ::tracing::error!("failed to dump state to {}: {e}", path.display());
+        }
+
+        let mut reopen = false;
         egui::TopBottomPanel::top("controls-panel")
             .exact_height(25.0)
             .show(ctx, |ui| {
@@ -22,28 +94,175 @@ impl Application {
                         ui.toggle_value(&mut self.show_breakpoints, "Breakpoints");
                         ui.toggle_value(&mut self.show_graph, "Graph");
                         ui.toggle_value(&mut self.show_errors, "Errors");
+                        ui.toggle_value(&mut self.show_profiler, "Profiler");
+                        ui.toggle_value(&mut self.show_event_counts, "Load");
+                        ui.toggle_value(&mut self.show_activity, "Activity")
+                            .on_hover_text("When each module handled events");
+                        ui.toggle_value(&mut self.show_runtime_events, "Runtime");
+                        ui.toggle_value(&mut self.show_log_view, "Logs")
+                            .on_hover_text("The logs of all modules");
+                        if let Some(last) = self.closed.last() {
+                            reopen = ui
+                                .button("Reopen Closed")
+                                .on_hover_text(format!("Reopens the inspector of {}", last.path))
+                                .clicked();
+                        }
+
+                        ui.menu_button("Log Colors", |ui| {
+                            let mut colors = self.logs.colors();
+                            for level in [
+                                Level::TRACE,
+                                Level::DEBUG,
+                                Level::INFO,
+                                Level::WARN,
+                                Level::ERROR,
+                            ] {
+                                ui.horizontal(|ui| {
+                                    ui.color_edit_button_srgba(colors.get_mut(level));
+                                    ui.label(
+                                        RichText::new(level.as_str()).color(colors.get(level)),
+                                    );
+                                });
+                            }
+                            if ui.button("Reset").clicked() {
+                                colors = LogColors::default();
+                            }
+                            self.logs.set_colors(colors);
+                        });
+                        ui.menu_button("Log Retention", |ui| {
+                            let mut max_events = self.logs.max_events();
+                            let mut max_merged = self.logs.max_merged();
+                            ui.horizontal(|ui| {
+                                ui.label("Keep the last");
+                                ui.add(DragValue::new(&mut max_events).range(1..=usize::MAX));
+                                ui.label("events per module");
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Keep the last");
+                                ui.add(DragValue::new(&mut max_merged).range(1..=usize::MAX));
+                                ui.label("events in the log view");
+                            });
+                            if ui.button("Reset").clicked() {
+                                max_events = DEFAULT_MAX_EVENTS;
+                                max_merged = DEFAULT_MAX_MERGED;
+                            }
+                            if max_events != self.logs.max_events() {
+                                self.logs.set_max_events(max_events);
+                            }
+                            if max_merged != self.logs.max_merged() {
+                                self.logs.set_max_merged(max_merged);
+                            }
+                        });
+                        ComboBox::from_id_salt("log-break-level")
+                            .selected_text(match self.param.log_break_level {
+                                Some(level) => format!("Stop on {level}"),
+                                None => "Stop on log".to_string(),
+                            })
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.param.log_break_level, None, "Never");
+                                for level in [Level::ERROR, Level::WARN, Level::INFO] {
+                                    ui.selectable_value(
+                                        &mut self.param.log_break_level,
+                                        Some(level),
+                                        format!("{level} and above"),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text("Stop once any module logs at this level");
+                        ui.menu_button("Save Logs", |ui| {
+                            for (format, name, file) in [
+                                (LogFormat::Json, "As JSON lines", "logs.jsonl"),
+                                (LogFormat::Text, "As text", "logs.txt"),
+                            ] {
+                                if ui.button(name).clicked() {
+                                    self.logs_format = format;
+                                    self.logs_dialog = FileDialog::new()
+                                        .initial_directory(self.dir.clone())
+                                        .default_file_name(file);
+                                    self.logs_dialog.save_file();
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                        self.render_recorder_menu(ui);
                     });
 
                     ui.with_layout(Layout::right_to_left(Align::TOP), |ui| {
                         if ui
-                            .add(egui::Button::new("Stop").fill(Color32::RED))
+                            .add_enabled(!finished, egui::Button::new("Stop").fill(Color32::RED))
                             .clicked()
                         {
-                            self.param.limit = Some(0);
+                            self.set_run(RunState::Paused);
+                        }
+                        if ui.button("Reset").clicked() {
+                            self.reset();
+                        }
+                        if ui
+                            .add_enabled(
+                                self.timeline.latest_before(itr).is_some(),
+                                egui::Button::new("Rewind"),
+                            )
+                            .on_hover_text("Return to the previous timeline checkpoint")
+                            .clicked()
+                        {
+                            self.rewind(itr);
+                        }
+                        if ui.button("Set as baseline").clicked() {
+                            self.baseline = Some(Baseline { time, events: itr });
+                        }
+                        if let Some(baseline) = self.baseline {
+                            if ui
+                                .button(
+                                    RichText::new(format!(
+                                        "Baseline {:?} | {}",
+                                        baseline.time, baseline.events
+                                    ))
+                                    .color(Color32::YELLOW),
+                                )
+                                .on_hover_text("Reset restores this point. Click to clear.")
+                                .clicked()
+                            {
+                                self.baseline = None;
+                            }
                         }
                         ui.separator();
 
                         if ui
-                            .add(egui::Button::new("Start").fill(Color32::GREEN))
+                            .add_enabled(!finished, egui::Button::new("Start").fill(Color32::GREEN))
                             .clicked()
                         {
-                            self.param.limit = None;
+                            self.log_hit = None;
+                            self.set_run(RunState::Running);
                         }
                         if ui
-                            .add(egui::Button::new("Step").fill(Color32::DARK_GREEN))
+                            .add_enabled(
+                                !finished,
+                                egui::Button::new("Step").fill(Color32::DARK_GREEN),
+                            )
                             .clicked()
                         {
-                            self.param.limit = Some(1);
+                            self.set_run(RunState::Stepping(1));
+                        }
+                        if ui
+                            .add_enabled(itr > 0, egui::Button::new("Step Back"))
+                            .on_hover_text("Undo the last event by replaying up to the one before")
+                            .clicked()
+                        {
+                            self.step_back(itr);
+                        }
+                        let armed = self
+                            .breakpoints
+                            .iter()
+                            .any(|b| b.kind != BreakpointKind::Disabled);
+                        if ui
+                            .add_enabled(armed && !finished, egui::Button::new("Continue"))
+                            .on_hover_text("Run until the next breakpoint triggers")
+                            .clicked()
+                        {
+                            self.hit = None;
+                            self.log_hit = None;
+                            self.set_run(RunState::Running);
                         }
 
                         let slider = Slider::new(&mut self.param.per_frame_count, 1..=1_000)
@@ -52,8 +271,80 @@ impl Application {
                             .suffix(" events pre frame")
                             .logarithmic(true);
                         ui.add(slider);
+                        if let Some((at, count)) = self.throttled {
+                            if at.elapsed() < THROTTLE_WARNING {
+                                ui.label(
+                                    RichText::new(format!("slow, reduced to {count} per frame"))
+                                        .color(Color32::YELLOW),
+                                )
+                                .on_hover_text(format!(
+                                    "Dispatching took longer than {:?} per frame",
+                                    self.param.slow_frame
+                                ));
+                                ui.ctx().request_repaint_after(THROTTLE_WARNING);
+                            } else {
+                                self.throttled = None;
+                            }
+                        }
+
+                        ui.add(
+                            Slider::new(&mut self.param.sample_every, 1..=1_000)
+                                .integer()
+                                .logarithmic(true)
+                                .prefix("sample every ")
+                                .suffix(" events"),
+                        )
+                        .on_hover_text(
+                            "Reload inspected and plotted values less often. \
+                             Breakpoints and watches remain exact, they are checked after \
+                             every event.",
+                        );
+                        if self.param.sample_every > 1 {
+                            ui.label(RichText::new("breakpoints and watches remain exact").weak());
+                        }
 
-                        ui.label(format!("{:?} | {}", time, itr,));
+                        let mut uncapped = self.param.target_fps == 0;
+                        if ui
+                            .checkbox(&mut uncapped, "Uncapped")
+                            .on_hover_text("Render as fast as possible")
+                            .changed()
+                        {
+                            self.param.target_fps = if uncapped { 0 } else { DEFAULT_FPS };
+                        }
+                        if !uncapped {
+                            ui.add(
+                                Slider::new(&mut self.param.target_fps, 5..=240)
+                                    .integer()
+                                    .suffix(" fps"),
+                            );
+                        }
+
+                        self.show_progress(ui, time, itr);
+                        self.show_run_state(ui);
+                        ui.label(format!(
+                            "{:?} | {} | {:.0} ev/s | seed {}",
+                            time,
+                            itr,
+                            self.throughput.per_second(),
+                            self.seed
+                        ));
+                        let seed = self.seed_input.trim().parse::<u64>();
+                        if ui
+                            .add_enabled(seed.is_ok(), egui::Button::new("Restart with seed"))
+                            .on_hover_text("Rebuilds the runtime with this seed")
+                            .clicked()
+                            && let Ok(seed) = seed
+                        {
+                            self.restart_with_seed(seed);
+                        }
+                        ui.add(TextEdit::singleline(&mut self.seed_input).desired_width(80.0));
+                        if self.panic.is_some()
+                            && ui
+                                .button(RichText::new("A module panicked").color(Color32::RED))
+                                .clicked()
+                        {
+                            self.show_panic = !self.show_panic;
+                        }
                         if has_err {
                             if ui
                                 .button(RichText::new("Some error has occured").color(Color32::RED))
@@ -65,5 +356,174 @@ impl Application {
                     })
                 });
             });
+
+        if reopen {
+            self.reopen_closed();
+        }
+        if finished {
+            self.render_finished(ctx, time, itr);
+        }
+        self.render_log_hit(ctx);
+    }
+
+    /// The progress towards the time limit, or else the fraction of the
+    /// known events dispatched. Events scheduled later are not known yet,
+    /// so the latter may move backwards.
+    fn show_progress(&self, ui: &mut Ui, time: SimTime, events: usize) {
+        let Rt::Runtime(runtime) = &self.rt else {
+            return;
+        };
+        if self.run.is_paused() || runtime.num_events_remaining() == 0 {
+            return;
+        }
+
+        let (progress, text) = match (self.param.until, self.run) {
+            (Some(until), _) if until > SimTime::ZERO => {
+                let progress = time.as_secs_f64() / until.as_secs_f64();
+                (progress, format!("{:.0}% of {until:?}", progress * 100.0))
+            }
+            (_, RunState::Stepping(_)) => {
+                let total = events + runtime.num_events_remaining();
+                let progress = events as f64 / total as f64;
+                (
+                    progress,
+                    format!("{:.0}% of {total} events", progress * 100.0),
+                )
+            }
+            _ => {
+                ui.spinner()
+                    .on_hover_text("Running until a breakpoint triggers");
+                return;
+            }
+        };
+        ui.add(
+            ProgressBar::new(progress.clamp(0.0, 1.0) as f32)
+                .desired_width(120.0)
+                .text(text),
+        );
+    }
+
+    /// Whether the sim runs, next to the progress, since the shortcuts
+    /// change it without touching any button.
+    fn show_run_state(&self, ui: &mut Ui) {
+        let (text, color) = match self.run {
+            RunState::Running => ("running".to_string(), Color32::GREEN),
+            RunState::Paused => ("paused".to_string(), Color32::YELLOW),
+            RunState::Stepping(n) => (format!("stepping {n}"), Color32::LIGHT_GREEN),
+            RunState::Finished => ("finished".to_string(), Color32::GRAY),
+        };
+        ui.label(RichText::new(text).color(color))
+            .on_hover_text("Space toggles running, Right or N steps and Escape stops");
+    }
+
+    /// A banner once the sim ended, since stepping no longer does anything.
+    fn render_finished(&mut self, ctx: &Context, time: SimTime, events: usize) {
+        let mut reset = false;
+        TopBottomPanel::top("finished").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(format!(
+                        "Simulation finished at t={time:?} after {events} events"
+                    ))
+                    .strong()
+                    .color(Color32::LIGHT_GREEN),
+                );
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    reset = ui
+                        .button("Reset")
+                        .on_hover_text("Restart from the baseline")
+                        .clicked();
+                });
+            });
+        });
+        if reset {
+            self.reset();
+        }
+    }
+
+    /// The log line that stopped the sim, until dismissed or continued.
+    fn render_log_hit(&mut self, ctx: &Context) {
+        let Some(event) = &self.log_hit else {
+            return;
+        };
+        let level = *event.metadata.level();
+        let color = self.logs.colors().get(level);
+
+        let mut open = None;
+        let mut dismiss = false;
+        TopBottomPanel::top("log-hit").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(format!("Stopped on {level}"))
+                        .strong()
+                        .color(color),
+                );
+                ui.label(format!("[{}] {}", event.time, event.module));
+                if !event.span.is_empty() {
+                    ui.label(RichText::new(&event.span).weak());
+                }
+                ui.label(RichText::new(&event.fields).strong());
+
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    dismiss = ui.button("Dismiss").clicked();
+                    if ui.button("Open").clicked() {
+                        open = Some(event.module.clone());
+                    }
+                });
+            });
+        });
+
+        if let Some(path) = open {
+            self.open_inspector(path);
+        }
+        if dismiss {
+            self.log_hit = None;
+        }
+    }
+
+    /// Space toggles running, Right or N steps and Escape stops. Ignored
+    /// while typing, e.g. into a search box, or while the palette is open.
+    fn handle_shortcuts(&mut self, ctx: &Context) {
+        if self.palette.is_some() || ctx.memory(|m| m.focused().is_some()) {
+            return;
+        }
+
+        let (space, step, escape) = ctx.input(|i| {
+            (
+                i.key_pressed(Key::Space),
+                i.key_pressed(Key::ArrowRight) || i.key_pressed(Key::N),
+                i.key_pressed(Key::Escape),
+            )
+        });
+        if space {
+            self.set_run(self.run.toggled());
+        }
+        if step {
+            self.set_run(RunState::Stepping(1));
+        }
+        if escape {
+            self.set_run(RunState::Paused);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throughput_over_window() {
+        let t0 = Instant::now();
+        let mut throughput = Throughput::default();
+        throughput.record(t0, 0);
+        throughput.record(t0 + Duration::from_millis(500), 1_000);
+        assert_eq!(throughput.per_second(), 2_000.0);
+
+        // Older samples leave the window
+        throughput.record(t0 + Duration::from_millis(2_000), 1_000);
+        assert_eq!(throughput.per_second(), 0.0);
+
+        throughput.record(t0 + Duration::from_millis(2_100), 0);
+        assert_eq!(throughput.samples.len(), 1);
     }
 }
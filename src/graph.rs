@@ -0,0 +1,734 @@
+use std::{
+    f32::consts::TAU,
+    fmt::Write as _,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc::{Receiver, channel},
+    thread,
+};
+
+use des::prelude::*;
+use egui::{
+    Color32, FontId, Pos2, Rect, RichText, Sense, Stroke, StrokeKind, Ui, Vec2, Visuals, vec2,
+};
+use egui_file_dialog::FileDialog;
+use fxhash::FxHashMap;
+use petgraph::dot::{Config, Dot};
+use serde_norway::{Mapping, Value};
+
+use crate::{
+    Application,
+    channel::{CHANNEL_PREFIX, ChannelObserver, channel_path},
+    inspector::{Ctx, display},
+};
+
+const ITERATIONS: usize = 300;
+const ITERATIONS_PER_FRAME: usize = 10;
+const MARGIN: f32 = 60.0;
+
+/// An interactive view of `sim.topology()`.
+///
+/// Nodes are placed by a simple force-directed layout, that settles over
+/// the first frames. The topology is frozen once the runtime is built, so
+/// the layout is computed only once.
+#[derive(Debug)]
+pub struct TopologyView {
+    nodes: Vec<(ObjectPath, Vec2)>,
+    // source, target and the channel path of each edge
+    edges: Vec<(usize, usize, ObjectPath)>,
+    steps: usize,
+}
+
+impl TopologyView {
+    pub fn new(sim: &Sim<()>) -> Self {
+        let topo = sim.topology();
+        let graph = topo.map(
+            |_, node| node.path(),
+            |_, edge| channel_path(&edge.source, &edge.target),
+        );
+
+        let n = graph.node_count().max(1) as f32;
+        let nodes = graph
+            .node_weights()
+            .enumerate()
+            .map(|(i, path)| {
+                let angle = i as f32 / n * TAU;
+                (path.clone(), vec2(angle.cos(), angle.sin()))
+            })
+            .collect();
+        let edges = graph
+            .raw_edges()
+            .iter()
+            .map(|e| (e.source().index(), e.target().index(), e.weight.clone()))
+            .collect();
+
+        Self {
+            nodes,
+            edges,
+            steps: 0,
+        }
+    }
+
+    fn is_settled(&self) -> bool {
+        self.steps >= ITERATIONS
+    }
+
+    /// One Fruchterman-Reingold step with an ideal edge length of 1.
+    fn step(&mut self) {
+        let temperature = 0.1 * (1.0 - self.steps as f32 / ITERATIONS as f32);
+        let mut forces = vec![Vec2::ZERO; self.nodes.len()];
+
+        for i in 0..self.nodes.len() {
+            for j in (i + 1)..self.nodes.len() {
+                let delta = self.nodes[i].1 - self.nodes[j].1;
+                let d = delta.length().max(0.01);
+                let f = delta / d * (1.0 / d);
+                forces[i] += f;
+                forces[j] -= f;
+            }
+        }
+        for (s, t, _) in &self.edges {
+            let delta = self.nodes[*s].1 - self.nodes[*t].1;
+            let f = delta * delta.length();
+            forces[*s] -= f;
+            forces[*t] += f;
+        }
+
+        for ((_, pos), force) in self.nodes.iter_mut().zip(forces) {
+            let len = force.length();
+            if len > 0.0 {
+                *pos += force / len * len.min(temperature);
+            }
+        }
+        self.steps += 1;
+    }
+
+    /// Maps layout coordinates into `rect`.
+    fn transform(&self, rect: Rect) -> impl Fn(Vec2) -> Pos2 {
+        let (min, max) = self.nodes.iter().fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), (_, p)| (min.min(*p), max.max(*p)),
+        );
+        let inner = rect.shrink(MARGIN);
+        let size = (max - min).max(Vec2::splat(f32::EPSILON));
+        move |p| inner.min + (p - min) / size * inner.size()
+    }
+}
+
+/// A PNG rendering of the topology by graphviz `dot`.
+///
+/// `dot` runs on a background thread. Renders are cached on disk, keyed by
+/// a hash of the dot source, so an unchanged topology is not re-rendered.
+#[derive(Debug, Default)]
+pub struct PngExport {
+    pending: Option<Receiver<Result<PathBuf, String>>>,
+    result: Option<Result<PathBuf, String>>,
+}
+
+impl PngExport {
+    fn start(&mut self, sim: &Sim<()>, dir: &Path, theme: DotTheme) {
+        let dot = topology_dot(sim);
+        let hash = fxhash::hash64(&(&dot, theme.args()));
+        let path = dir.join(format!("topo-{hash:016x}.png"));
+
+        let (tx, rx) = channel();
+        if path.exists() {
+            let _ = tx.send(Ok(path));
+        } else {
+            thread::spawn(move || {
+                let result = render_png(&dot, &path, theme).map(|()| path);
+                let _ = tx.send(result);
+            });
+        }
+        self.pending = Some(rx);
+        self.result = None;
+    }
+
+    fn poll(&mut self) {
+        if let Some(rx) = &self.pending
+            && let Ok(result) = rx.try_recv()
+        {
+            if let Ok(path) = &result {
+                ::tracing::info!("wrote topo to {}", path.display());
+            }
+            self.result = Some(result);
+            self.pending = None;
+        }
+    }
+}
+
+fn topology_dot(sim: &Sim<()>) -> String {
+    let topo = sim.topology();
+    let graph = topo.map(
+        |_, node| node.path().to_string(),
+        |_, edge| format!("{}*{}", edge.source.name(), edge.target.name()),
+    );
+    let dot = Dot::with_attr_getters(
+        &graph,
+        &[Config::NodeNoLabel, Config::EdgeNoLabel],
+        &|_, edge| {
+            let (l, r) = edge.weight().split_once("*").unwrap();
+            format!("headlabel={r:?} taillabel={l:?}")
+        },
+        &|_, node| format!("label={:?} shape=box", node.1),
+    );
+    format!("{dot}")
+}
+
+/// The colors of a `dot` rendering, taken from the egui theme so that the
+/// PNG matches the app.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DotTheme {
+    pub background: Color32,
+    pub foreground: Color32,
+}
+
+impl DotTheme {
+    pub fn from_visuals(visuals: &Visuals) -> Self {
+        Self {
+            background: visuals.panel_fill,
+            foreground: visuals.text_color(),
+        }
+    }
+
+    fn args(&self) -> Vec<String> {
+        let fg = dot_color(self.foreground);
+        let mut args = ["G", "N", "E"]
+            .iter()
+            .flat_map(|kind| {
+                [
+                    format!("-{kind}fontcolor={fg}"),
+                    format!("-{kind}color={fg}"),
+                ]
+            })
+            .collect::<Vec<_>>();
+        args.push(format!("-Gbgcolor={}", dot_color(self.background)));
+        args
+    }
+}
+
+fn dot_color(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+fn render_png(dot: &str, path: &Path, theme: DotTheme) -> Result<(), String> {
+    let mut child = Command::new("dot")
+        .arg("-Tpng")
+        .arg("-Gdpi=300")
+        .args(theme.args())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => "graphviz `dot` was not found, is it installed?".to_string(),
+            _ => format!("failed to run dot: {e}"),
+        })?;
+
+    let mut stdin = child.stdin.take().expect("failed to open stdin");
+    stdin
+        .write_all(dot.as_bytes())
+        .map_err(|e| format!("failed to write to dot: {e}"))?;
+    drop(stdin);
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("dot failed: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "dot failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    fs::write(path, &output.stdout).map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
+
+const BREAKPOINT_MARK: Color32 = Color32::RED;
+const TRACE_MARK: Color32 = Color32::LIGHT_BLUE;
+
+/// How a module is being watched, marked in the module list and the graph.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Marks {
+    /// Whether any breakpoint is set on the module.
+    pub breakpoint: bool,
+    /// Whether the module is observed by a trace or a watch table.
+    pub traced: bool,
+}
+
+/// The marks of a module, as colored dots.
+pub fn show_marks(ui: &mut Ui, marks: Marks) {
+    if marks.breakpoint {
+        ui.label(RichText::new("●").color(BREAKPOINT_MARK))
+            .on_hover_text("Has breakpoints");
+    }
+    if marks.traced {
+        ui.label(RichText::new("●").color(TRACE_MARK))
+            .on_hover_text("Traced or watched");
+    }
+}
+
+/// A file format the topology can be exported as.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TopologyFormat {
+    #[default]
+    Dot,
+    GraphMl,
+    /// A node-link document, as read by e.g. networkx.
+    Json,
+}
+
+impl TopologyFormat {
+    const ALL: [Self; 3] = [Self::Dot, Self::GraphMl, Self::Json];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Dot => "DOT",
+            Self::GraphMl => "GraphML",
+            Self::Json => "JSON",
+        }
+    }
+
+    fn file_name(&self) -> &'static str {
+        match self {
+            Self::Dot => "topology.dot",
+            Self::GraphMl => "topology.graphml",
+            Self::Json => "topology.json",
+        }
+    }
+}
+
+/// The pending "Export Topology" file dialog.
+#[derive(Default)]
+pub struct TopologyExport {
+    dialog: FileDialog,
+    format: TopologyFormat,
+}
+
+/// The channel metrics written as GraphML edge attributes, with their type.
+const GRAPHML_METRICS: [(&str, &str); 4] = [
+    ("bitrate", "long"),
+    ("latency", "double"),
+    ("jitter", "double"),
+    ("drop_behaviour", "string"),
+];
+
+/// The topology with the metrics of each channel, as exported.
+///
+/// des does not expose the type of a module, so nodes are described by
+/// their path and name only.
+#[derive(Debug)]
+struct TopologyDocument {
+    nodes: Vec<ObjectPath>,
+    edges: Vec<DocumentEdge>,
+}
+
+#[derive(Debug)]
+struct DocumentEdge {
+    source: usize,
+    target: usize,
+    source_gate: String,
+    target_gate: String,
+    channel: Option<Mapping>,
+}
+
+impl TopologyDocument {
+    fn new(sim: &Sim<()>, channels: &mut ChannelObserver) -> Self {
+        let topo = sim.topology();
+        let graph = topo.map(
+            |_, node| node.path(),
+            |_, edge| {
+                (
+                    edge.source.name().to_string(),
+                    edge.target.name().to_string(),
+                    channel_path(&edge.source, &edge.target),
+                )
+            },
+        );
+
+        Self {
+            nodes: graph.node_weights().cloned().collect(),
+            edges: graph
+                .raw_edges()
+                .iter()
+                .map(|e| {
+                    let (source_gate, target_gate, path) = &e.weight;
+                    DocumentEdge {
+                        source: e.source().index(),
+                        target: e.target().index(),
+                        source_gate: source_gate.clone(),
+                        target_gate: target_gate.clone(),
+                        channel: channels.load(sim, path),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|path| serde_json::json!({ "id": path.as_str(), "name": node_name(path) }))
+            .collect::<Vec<_>>();
+        let links = self
+            .edges
+            .iter()
+            .map(|edge| {
+                serde_json::json!({
+                    "source": self.nodes[edge.source].as_str(),
+                    "target": self.nodes[edge.target].as_str(),
+                    "source_gate": edge.source_gate,
+                    "target_gate": edge.target_gate,
+                    "channel": edge.channel,
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::json!({
+            "directed": true,
+            "multigraph": true,
+            "graph": {},
+            "nodes": nodes,
+            "links": links,
+        })
+    }
+
+    fn to_graphml(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(
+            out,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )
+        .unwrap();
+        for (id, name) in [("path", "path"), ("name", "name")] {
+            writeln!(
+                out,
+                r#"  <key id="{id}" for="node" attr.name="{name}" attr.type="string"/>"#
+            )
+            .unwrap();
+        }
+        for id in ["source_gate", "target_gate"] {
+            writeln!(
+                out,
+                r#"  <key id="{id}" for="edge" attr.name="{id}" attr.type="string"/>"#
+            )
+            .unwrap();
+        }
+        for (id, ty) in GRAPHML_METRICS {
+            writeln!(
+                out,
+                r#"  <key id="{id}" for="edge" attr.name="{id}" attr.type="{ty}"/>"#
+            )
+            .unwrap();
+        }
+
+        writeln!(out, r#"  <graph id="topology" edgedefault="directed">"#).unwrap();
+        for (i, path) in self.nodes.iter().enumerate() {
+            writeln!(out, r#"    <node id="n{i}">"#).unwrap();
+            writeln!(
+                out,
+                r#"      <data key="path">{}</data>"#,
+                xml_escape(path.as_str())
+            )
+            .unwrap();
+            writeln!(
+                out,
+                r#"      <data key="name">{}</data>"#,
+                xml_escape(node_name(path))
+            )
+            .unwrap();
+            writeln!(out, "    </node>").unwrap();
+        }
+        for (i, edge) in self.edges.iter().enumerate() {
+            writeln!(
+                out,
+                r#"    <edge id="e{i}" source="n{}" target="n{}">"#,
+                edge.source, edge.target
+            )
+            .unwrap();
+            for (key, value) in [
+                ("source_gate", &edge.source_gate),
+                ("target_gate", &edge.target_gate),
+            ] {
+                writeln!(
+                    out,
+                    r#"      <data key="{key}">{}</data>"#,
+                    xml_escape(value)
+                )
+                .unwrap();
+            }
+            for (key, _) in GRAPHML_METRICS {
+                let Some(value) = edge.channel.as_ref().and_then(|c| c.get(key)) else {
+                    continue;
+                };
+                let value = match value {
+                    Value::String(s) => s.clone(),
+                    other => serde_norway::to_string(other).unwrap().trim().to_string(),
+                };
+                writeln!(
+                    out,
+                    r#"      <data key="{key}">{}</data>"#,
+                    xml_escape(&value)
+                )
+                .unwrap();
+            }
+            writeln!(out, "    </edge>").unwrap();
+        }
+        writeln!(out, "  </graph>").unwrap();
+        writeln!(out, "</graphml>").unwrap();
+        out
+    }
+}
+
+/// The last segment of a module path.
+fn node_name(path: &ObjectPath) -> &str {
+    path.as_str().rsplit('.').next().unwrap_or_default()
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The distance of `p` to the segment from `a` to `b`.
+fn distance_to_segment(p: Pos2, a: Pos2, b: Pos2) -> f32 {
+    let ab = b - a;
+    let t = ((p - a).dot(ab) / ab.length_sq().max(f32::EPSILON)).clamp(0.0, 1.0);
+    p.distance(a + ab * t)
+}
+
+impl Application {
+    /// The marks of all modules that are watched in some way.
+    pub fn marks(&self) -> FxHashMap<ObjectPath, Marks> {
+        self.rt
+            .sim()
+            .nodes()
+            .into_iter()
+            .filter_map(|path| {
+                let marks = Marks {
+                    breakpoint: self.breakpoints.iter().any(|b| b.path == path),
+                    traced: self.traces.iter().flatten().any(|t| t.needs_path(&path))
+                        || self.watches.iter().any(|w| w.path == path),
+                };
+                (marks.breakpoint || marks.traced).then_some((path, marks))
+            })
+            .collect()
+    }
+
+    fn export_topology(&mut self, path: &Path) -> io::Result<()> {
+        let sim = self.rt.sim();
+        let contents = match self.topo_export.format {
+            TopologyFormat::Dot => topology_dot(sim),
+            TopologyFormat::GraphMl => {
+                TopologyDocument::new(sim, &mut self.observe.channels).to_graphml()
+            }
+            TopologyFormat::Json => {
+                let document = TopologyDocument::new(sim, &mut self.observe.channels);
+                serde_json::to_string_pretty(&document.to_json())?
+            }
+        };
+        fs::write(path, contents)
+    }
+
+    pub fn render_graph(&mut self, ui: &mut Ui) {
+        self.png.poll();
+        self.topo_export.dialog.update(ui.ctx());
+        if let Some(path) = self.topo_export.dialog.take_picked() {
+            match self.export_topology(&path) {
+                Ok(()) => ::tracing::info!("wrote topology to {}", path.display()),
+                Err(e) => ::tracing::error!("failed to write {}: {e}", path.display()),
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.menu_button("Export Topology", |ui| {
+                for format in TopologyFormat::ALL {
+                    if ui.button(format.name()).clicked() {
+                        self.topo_export = TopologyExport {
+                            dialog: FileDialog::new()
+                                .initial_directory(self.dir.clone())
+                                .default_file_name(format.file_name()),
+                            format,
+                        };
+                        self.topo_export.dialog.save_file();
+                        ui.close_menu();
+                    }
+                }
+            });
+            let pending = self.png.pending.is_some();
+            if ui
+                .add_enabled(!pending, egui::Button::new("Save as PNG"))
+                .on_hover_text("Renders the topology with graphviz dot")
+                .clicked()
+            {
+                let theme = DotTheme::from_visuals(&ui.ctx().style().visuals);
+                self.png.start(self.rt.sim(), &self.dir, theme);
+            }
+            if pending {
+                ui.spinner();
+                ui.ctx().request_repaint();
+            }
+            match &self.png.result {
+                Some(Ok(path)) => {
+                    ui.label(format!("Saved to {}", path.display()));
+                }
+                Some(Err(e)) => {
+                    ui.label(RichText::new(e).color(Color32::RED));
+                }
+                None => {}
+            }
+        });
+
+        let marks = self.marks();
+        let sim = self.rt.sim();
+        let view = self.graph.get_or_insert_with(|| TopologyView::new(sim));
+        if !view.is_settled() {
+            for _ in 0..ITERATIONS_PER_FRAME {
+                view.step();
+            }
+            ui.ctx().request_repaint();
+        }
+
+        let (resp, painter) = ui.allocate_painter(ui.available_size(), Sense::hover());
+        let to_screen = view.transform(resp.rect);
+        let pointer = resp.hover_pos();
+        let stroke = ui.visuals().widgets.noninteractive.fg_stroke;
+
+        let mut hovered = None;
+        for (i, (s, t, _)) in view.edges.iter().enumerate() {
+            let (a, b) = (to_screen(view.nodes[*s].1), to_screen(view.nodes[*t].1));
+            let is_hovered =
+                hovered.is_none() && pointer.is_some_and(|p| distance_to_segment(p, a, b) < 4.0);
+            let stroke = match is_hovered {
+                true => Stroke::new(2.0, Color32::YELLOW),
+                false => stroke,
+            };
+            if is_hovered {
+                hovered = Some(i);
+            }
+
+            painter.line_segment([a, b], stroke);
+            let dir = (b - a).normalized() * 12.0;
+            painter.arrow(a + (b - a) * 0.5 - dir * 0.5, dir, stroke);
+        }
+
+        let mut open = None;
+        for (i, (path, pos)) in view.nodes.iter().enumerate() {
+            let galley = painter.layout_no_wrap(
+                path.to_string(),
+                FontId::proportional(14.0),
+                ui.visuals().strong_text_color(),
+            );
+            let rect = Rect::from_center_size(to_screen(*pos), galley.size() + vec2(12.0, 8.0));
+            let node = ui
+                .interact(rect, resp.id.with(("node", i)), Sense::click())
+                .on_hover_text("Open the module inspector");
+
+            let fill = match node.hovered() {
+                true => ui.visuals().widgets.hovered.bg_fill,
+                false => ui.visuals().widgets.inactive.bg_fill,
+            };
+            painter.rect(rect, 4.0, fill, stroke, StrokeKind::Inside);
+            painter.galley(rect.center() - galley.size() / 2.0, galley, Color32::WHITE);
+            if let Some(marks) = marks.get(path) {
+                if marks.breakpoint {
+                    painter.circle_filled(rect.right_top(), 4.0, BREAKPOINT_MARK);
+                }
+                if marks.traced {
+                    painter.circle_filled(rect.left_top(), 4.0, TRACE_MARK);
+                }
+            }
+            if node.clicked() {
+                open = Some(path.clone());
+            }
+        }
+
+        if let Some(i) = hovered {
+            let path = &view.edges[i].2;
+            let metrics = self.observe.channels.load(sim, path);
+            resp.on_hover_ui_at_pointer(|ui| {
+                ui.label(path.as_str().trim_start_matches(CHANNEL_PREFIX));
+                match metrics {
+                    Some(metrics) => display(
+                        ui,
+                        Ctx {
+                            node: path,
+                            actions: None,
+                            nav: None,
+                            changed: None,
+                            sparks: None,
+                            breakpoints: None,
+                        },
+                        &Value::Mapping(metrics),
+                        String::new(),
+                    ),
+                    None => {
+                        ui.label("No channel");
+                    }
+                }
+            });
+        }
+
+        if let Some(path) = open {
+            self.open_inspector(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::pos2;
+
+    use super::*;
+
+    #[test]
+    fn dot_follows_theme() {
+        let light = DotTheme::from_visuals(&Visuals::light()).args();
+        let dark = DotTheme::from_visuals(&Visuals::dark()).args();
+        assert_ne!(light, dark);
+        assert_eq!(light.len(), 7);
+        assert!(light.contains(&format!(
+            "-Gbgcolor={}",
+            dot_color(Visuals::light().panel_fill)
+        )));
+        assert_eq!(dot_color(Color32::WHITE), "#ffffff");
+    }
+
+    #[test]
+    fn exports_graphml_and_json() {
+        let document = TopologyDocument {
+            nodes: vec![ObjectPath::from("net.ping"), ObjectPath::from("net.pong")],
+            edges: vec![DocumentEdge {
+                source: 0,
+                target: 1,
+                source_gate: "out".to_string(),
+                target_gate: "in".to_string(),
+                channel: Some(Mapping::from_iter([
+                    (Value::from("bitrate"), Value::from(1000)),
+                    (Value::from("drop_behaviour"), Value::from("<Drop>")),
+                ])),
+            }],
+        };
+
+        let graphml = document.to_graphml();
+        assert!(graphml.contains(r#"<data key="name">ping</data>"#));
+        assert!(graphml.contains(r#"<edge id="e0" source="n0" target="n1">"#));
+        assert!(graphml.contains(r#"<data key="bitrate">1000</data>"#));
+        assert!(graphml.contains(r#"<data key="drop_behaviour">&lt;Drop&gt;</data>"#));
+
+        let json = document.to_json();
+        assert_eq!(json["links"][0]["source"], "net.ping");
+        assert_eq!(json["links"][0]["channel"]["bitrate"], 1000);
+    }
+
+    #[test]
+    fn segment_distance() {
+        let (a, b) = (pos2(0.0, 0.0), pos2(10.0, 0.0));
+        assert_eq!(distance_to_segment(pos2(5.0, 3.0), a, b), 3.0);
+        assert_eq!(distance_to_segment(pos2(-4.0, 3.0), a, b), 5.0);
+    }
+}
@@ -0,0 +1,74 @@
+use std::ops::ControlFlow;
+
+use des::{prelude::*, time::SimTime};
+
+use crate::{Breakpoint, ExecutionParameters, Observer, Stop, step_event};
+
+/// Runs the sim without a GUI, using the same breakpoint engine.
+///
+/// Stops once a breakpoint triggers, a module panics, `params.limit` events
+/// were dispatched, `params.until` is reached or the sim ends. The reason
+/// is printed, and the sim is returned for further assertions, e.g. in CI.
+/// After a panic the sim is returned as is, since finishing it would run
+/// the `at_sim_end` handlers of the broken modules.
+pub fn run_headless(
+    f: impl FnOnce() -> Runtime<Sim<()>>,
+    params: ExecutionParameters,
+    mut breakpoints: Vec<Breakpoint>,
+) -> Sim<()> {
+    let mut runtime = f();
+    runtime.start().expect("failed to start");
+
+    let mut observe = Observer::default();
+    for b in &mut breakpoints {
+        if !observe.contains_key(&b.path)
+            && let Some(value) = observe.load(&runtime.app, &b.path)
+        {
+            observe.insert(b.path.clone(), value);
+        }
+        b.reset(&observe);
+    }
+
+    let mut dispatched = 0;
+    while runtime.num_events_remaining() > 0 && !runtime.has_reached_limit() {
+        if params.limit.is_some_and(|limit| dispatched >= limit) {
+            println!("stopped after {dispatched} events");
+            break;
+        }
+        if params.until.is_some_and(|until| SimTime::now() >= until) {
+            println!("stopped at {:?}", SimTime::now());
+            break;
+        }
+
+        let flow = step_event(
+            &mut runtime,
+            &mut observe,
+            &mut breakpoints,
+            |_| false,
+            true,
+            |_| {},
+        );
+        dispatched += 1;
+        match flow {
+            ControlFlow::Break(Stop::Breakpoint(i)) => {
+                let b = &breakpoints[i];
+                println!(
+                    "breakpoint {:?} on {} {} triggered at {:?} with {:?}",
+                    b.kind,
+                    b.path,
+                    b.key,
+                    SimTime::now(),
+                    b.last
+                );
+                break;
+            }
+            ControlFlow::Break(Stop::Panic(message)) => {
+                println!("a module panicked at {:?}: {message}", SimTime::now());
+                return runtime.app;
+            }
+            ControlFlow::Continue(()) => {}
+        }
+    }
+
+    runtime.finish().app
+}
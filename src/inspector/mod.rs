@@ -1,17 +1,39 @@
-use std::{borrow::Cow, fs::File, io::BufWriter, sync::mpsc::Sender};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::mpsc::Sender,
+    time::{Duration, Instant},
+};
 
 use des::net::ObjectPath;
 
 use egui::{
-    Button, CollapsingHeader, Color32, Frame, Label, RichText, Sense, TextEdit, TextStyle,
+    Align, Button, CollapsingHeader, Color32, ComboBox, Frame, Grid, Key, Label, Modal,
+    ProgressBar, RichText, ScrollArea, Sense, TextEdit, TextStyle,
     collapsing_header::CollapsingState,
 };
 use egui_extras::{Column, TableBuilder};
-use fxhash::FxHashMap;
+use fxhash::{FxHashMap, FxHashSet};
+use regex::Regex;
 use serde_norway::{Mapping, Value};
+use span_tree::SpanTree;
+use sparkline::{Sparklines, sparkline};
 use tracing::Level;
 
-use crate::{ActionReq, tracing::GuiTracingObserver};
+mod span_tree;
+mod sparkline;
+
+use crate::{
+    ActionReq, Combine, TreeTraceReq,
+    channel::{GateInfo, is_channel_path},
+    plot::{TRACE_COLORS, siblings_pattern},
+    tracing::GuiTracingObserver,
+};
 
 #[derive(Debug, Clone)]
 pub struct ModuleInspector {
@@ -20,8 +42,192 @@ pub struct ModuleInspector {
     pub highlight: Option<String>,
     pub logs: GuiTracingObserver,
     pub remove: bool,
+    pub nav: Nav,
+    /// When the last property edit was rejected.
+    pub rejected: Option<Instant>,
+    /// Whether `filter` is a regex rather than a substring.
+    pub regex: bool,
+    // The last compiled filter and its pattern, to not recompile every frame.
+    compiled: Option<(String, Result<Regex, regex::Error>)>,
+    /// Which of `LEVELS` are shown in the log table.
+    pub levels: [bool; LEVELS.len()],
+    /// Known module paths, rendered as links when mentioned in logs.
+    pub nodes: Vec<ObjectPath>,
+    /// A frozen copy of the value, compared against the live one.
+    pub pinned: Option<Value>,
+    /// Another module, shown side by side with this one.
+    pub compare: Option<ObjectPath>,
+    /// Whether the log table sticks to the newest entry.
+    pub follow: bool,
+    /// The recent values of numeric keys, drawn next to them.
+    pub sparks: Sparklines,
+    /// Whether logs are grouped by their spans instead of listed.
+    pub group_spans: bool,
+    /// What the search box filters.
+    pub scope: FilterScope,
+    /// Event fields shown as columns of their own.
+    pub columns: Vec<String>,
+    /// The column the logs are sorted by, and whether descending.
+    pub sort: Option<(String, bool)>,
+    // The filter the matching props were last expanded for.
+    expanded: String,
 }
 
+/// What the search box of an inspector filters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterScope {
+    Logs,
+    Props,
+    Both,
+}
+
+impl FilterScope {
+    fn logs(self) -> bool {
+        self != Self::Props
+    }
+
+    fn props(self) -> bool {
+        self != Self::Logs
+    }
+}
+
+/// The file format of a dumped module state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateFormat {
+    Yaml,
+    Json,
+}
+
+impl StateFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Yaml => "yaml",
+            Self::Json => "json",
+        }
+    }
+
+    /// Writes `value` to `path`, as shown by the inspector.
+    pub fn save(self, path: &Path, value: &Value) -> io::Result<()> {
+        self.write(BufWriter::new(File::create(path)?), value)
+    }
+
+    fn write(self, mut w: impl Write, value: &Value) -> io::Result<()> {
+        match self {
+            Self::Yaml => serde_norway::to_writer(&mut w, value).map_err(io::Error::other)?,
+            Self::Json => {
+                serde_json::to_writer_pretty(&mut w, value)?;
+                writeln!(w)?;
+            }
+        }
+        w.flush()
+    }
+}
+
+/// The log filter of an inspector, kept when it is closed so that it is
+/// restored once the module is inspected again.
+#[derive(Debug, Clone)]
+pub struct LogFilter {
+    filter: String,
+    regex: bool,
+    levels: [bool; LEVELS.len()],
+    scope: FilterScope,
+}
+
+pub(crate) const LEVELS: [Level; 5] = [
+    Level::TRACE,
+    Level::DEBUG,
+    Level::INFO,
+    Level::WARN,
+    Level::ERROR,
+];
+
+/// Keyboard navigation state of the property tree.
+#[derive(Debug, Clone)]
+pub struct Nav {
+    pub focused: Option<String>,
+    pub command: Option<NavCommand>,
+    /// The keys of all rows rendered in the last frame, in order.
+    rows: RefCell<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NavCommand {
+    Expand,
+    Collapse,
+    AddBreakpoint,
+}
+
+impl Nav {
+    pub const fn new() -> Self {
+        Self {
+            focused: None,
+            command: None,
+            rows: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Moves the focus and issues commands from the arrow keys and Enter.
+    fn handle_keys(&mut self, ui: &egui::Ui) {
+        self.command = None;
+        let rows = self.rows.take();
+
+        // Typing into the search box must not move the focus
+        if ui.memory(|m| m.focused().is_some()) {
+            return;
+        }
+
+        let (up, down, left, right, enter, escape) = ui.input(|i| {
+            (
+                i.key_pressed(Key::ArrowUp),
+                i.key_pressed(Key::ArrowDown),
+                i.key_pressed(Key::ArrowLeft),
+                i.key_pressed(Key::ArrowRight),
+                i.key_pressed(Key::Enter),
+                i.key_pressed(Key::Escape),
+            )
+        });
+
+        let pos = self
+            .focused
+            .as_ref()
+            .and_then(|focused| rows.iter().position(|row| row == focused));
+        if down {
+            self.focused = match pos {
+                Some(pos) => rows.get(pos + 1).or(rows.last()),
+                None => rows.first(),
+            }
+            .cloned();
+        }
+        if up {
+            self.focused = match pos {
+                Some(pos) => rows.get(pos.saturating_sub(1)),
+                None => rows.last(),
+            }
+            .cloned();
+        }
+        if escape {
+            self.focused = None;
+        }
+
+        self.command = if right {
+            Some(NavCommand::Expand)
+        } else if left {
+            Some(NavCommand::Collapse)
+        } else if enter {
+            Some(NavCommand::AddBreakpoint)
+        } else {
+            None
+        };
+    }
+
+    fn is_focused(&self, key: &str) -> bool {
+        self.focused.as_deref() == Some(key)
+    }
+}
+
+const REJECT_FLASH: Duration = Duration::from_secs(1);
+const BREAKPOINT_HINT: &str = "Add a breakpoint, remove it in the breakpoint panel";
+
 impl PartialEq for ModuleInspector {
     fn eq(&self, other: &Self) -> bool {
         self.path == other.path
@@ -29,83 +235,410 @@ impl PartialEq for ModuleInspector {
 }
 
 impl ModuleInspector {
-    pub const fn new(module: ObjectPath, logs: GuiTracingObserver) -> Self {
+    pub fn new(module: ObjectPath, logs: GuiTracingObserver) -> Self {
         Self {
             path: module,
             filter: String::new(),
             logs,
             highlight: None,
             remove: false,
+            nav: Nav::new(),
+            rejected: None,
+            regex: false,
+            compiled: None,
+            levels: [true; LEVELS.len()],
+            nodes: Vec::new(),
+            pinned: None,
+            compare: None,
+            follow: true,
+            sparks: Sparklines::default(),
+            group_spans: false,
+            scope: FilterScope::Both,
+            expanded: String::new(),
+            columns: Vec::new(),
+            sort: None,
         }
     }
 }
 
 impl ModuleInspector {
-    pub fn show(&mut self, ui: &mut egui::Ui, value: Value, tx: Sender<ActionReq>) {
+    pub fn log_filter(&self) -> LogFilter {
+        LogFilter {
+            filter: self.filter.clone(),
+            regex: self.regex,
+            levels: self.levels,
+            scope: self.scope,
+        }
+    }
+
+    pub fn restore_log_filter(&mut self, filter: LogFilter) {
+        self.filter = filter.filter;
+        self.regex = filter.regex;
+        self.levels = filter.levels;
+        self.scope = filter.scope;
+    }
+
+    /// The compiled filter if in regex mode, recompiled only on change.
+    fn filter_regex(&mut self) -> Option<&Result<Regex, regex::Error>> {
+        if !self.regex {
+            return None;
+        }
+        if self
+            .compiled
+            .as_ref()
+            .is_none_or(|(pattern, _)| *pattern != self.filter)
+        {
+            self.compiled = Some((self.filter.clone(), Regex::new(&self.filter)));
+        }
+        self.compiled.as_ref().map(|(_, regex)| regex)
+    }
+
+    /// The branches of `value` matching the filter, if it applies to props.
+    fn filter_props<'a>(&self, value: &'a Value) -> Cow<'a, Value> {
+        if !self.scope.props() || self.filter.is_empty() {
+            return Cow::Borrowed(value);
+        }
+        let pruned = match self.compiled.as_ref().filter(|_| self.regex) {
+            Some((_, Ok(regex))) => prune(value, "", &|s| regex.is_match(s)),
+            // An invalid pattern filters nothing until fixed
+            Some((_, Err(_))) => return Cow::Borrowed(value),
+            None => prune(value, "", &|s| s.contains(self.filter.as_str())),
+        };
+        Cow::Owned(pruned.unwrap_or_else(|| Value::Mapping(Mapping::new())))
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        value: Value,
+        other: Option<&Value>,
+        gates: &[GateInfo],
+        breakpoints: &FxHashSet<String>,
+        tx: Sender<ActionReq>,
+    ) {
+        let colors = self.logs.colors();
         Frame::new().show(ui, |ui| {
             ui.horizontal(|ui| {
+                let invalid = self.filter_regex().is_some_and(|regex| regex.is_err());
                 TextEdit::singleline(&mut self.filter)
-                    .background_color(Color32::from_black_alpha(0))
+                    .background_color(match invalid {
+                        true => Color32::DARK_RED,
+                        false => Color32::from_black_alpha(0),
+                    })
                     .clip_text(true)
                     .hint_text("Search...")
                     .show(ui);
+                ui.toggle_value(&mut self.regex, ".*")
+                    .on_hover_text("Filter with a regex");
+                ComboBox::from_id_salt((&self.path, "scope"))
+                    .selected_text(match self.scope {
+                        FilterScope::Logs => "in logs",
+                        FilterScope::Props => "in props",
+                        FilterScope::Both => "in both",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.scope, FilterScope::Logs, "Logs");
+                        ui.selectable_value(&mut self.scope, FilterScope::Props, "Props");
+                        ui.selectable_value(&mut self.scope, FilterScope::Both, "Both");
+                    });
 
                 if ui.button("Export").clicked() {
-                    // Export logic
-                    let lock = self.logs.streams.lock().unwrap();
-                    let events = lock
-                        .get(&self.path)
-                        .unwrap()
-                        .output()
-                        .into_iter()
-                        .collect::<Vec<_>>();
-                    // Export events to file or clipboard
-                    let f = File::create(format!("{}.logs.yaml", self.path)).unwrap();
-                    let f = BufWriter::new(f);
-                    serde_norway::to_writer(f, &events).unwrap();
+                    tx.send(ActionReq::ExportLogs(self.path.clone()))
+                        .expect("failed to send");
+                }
+                ui.menu_button("Dump state", |ui| {
+                    for (format, name) in [
+                        (StateFormat::Yaml, "As YAML"),
+                        (StateFormat::Json, "As JSON"),
+                    ] {
+                        if ui.button(name).clicked() {
+                            tx.send(ActionReq::DumpState((
+                                self.path.clone(),
+                                value.clone(),
+                                format,
+                            )))
+                            .expect("failed to send");
+                            ui.close_menu();
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Writes the current state to a file, e.g. to compare it offline");
+
+                let mut pin = self.pinned.is_some();
+                if ui
+                    .toggle_value(&mut pin, "Pin")
+                    .on_hover_text("Freeze the current value to compare it to later ones")
+                    .changed()
+                {
+                    self.pinned = pin.then(|| value.clone());
+                }
+
+                if ui.button("Expand All").clicked() {
+                    set_all_open(ui.ctx(), &self.path, &value, "", true);
+                }
+                if ui.button("Collapse All").clicked() {
+                    set_all_open(ui.ctx(), &self.path, &value, "", false);
+                }
+
+                ComboBox::from_id_salt((&self.path, "compare"))
+                    .selected_text(match &self.compare {
+                        Some(path) => format!("vs {path}"),
+                        None => "Compare with…".to_string(),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.compare, None, "None");
+                        for node in self.nodes.iter().filter(|n| **n != self.path) {
+                            ui.selectable_value(
+                                &mut self.compare,
+                                Some(node.clone()),
+                                node.as_str(),
+                            );
+                        }
+                    });
+            });
+
+            let mut jump = false;
+            ui.horizontal(|ui| {
+                for (level, shown) in LEVELS.iter().zip(&mut self.levels) {
+                    ui.toggle_value(
+                        shown,
+                        RichText::new(level.as_str()).color(colors.get(*level)),
+                    );
                 }
+                ui.separator();
+                jump = ui
+                    .toggle_value(&mut self.follow, "Follow")
+                    .on_hover_text("Keep the newest logs in view")
+                    .changed()
+                    && self.follow;
+                ui.toggle_value(&mut self.group_spans, "By span")
+                    .on_hover_text("Group the logs by the spans they were emitted in");
             });
 
             ui.separator();
 
+            if let Some(rejected) = self.rejected {
+                if rejected.elapsed() < REJECT_FLASH {
+                    ui.label(
+                        RichText::new("Edit rejected, see the logs")
+                            .color(Color32::WHITE)
+                            .background_color(Color32::RED),
+                    );
+                    ui.ctx().request_repaint();
+                } else {
+                    self.rejected = None;
+                }
+            }
+
+            self.nav.handle_keys(ui);
+            self.sparks.record(&value);
+
+            let shown = self.filter_props(&value);
+            let filtering = matches!(shown, Cow::Owned(_));
+            if filtering && self.expanded != self.filter {
+                set_all_open(ui.ctx(), &self.path, &shown, "", true);
+                self.expanded = self.filter.clone();
+            } else if !filtering {
+                self.expanded.clear();
+            }
+            if filtering && shown.as_mapping().is_some_and(Mapping::is_empty) {
+                ui.weak("No props match the filter");
+            }
+
             // println!("{value:?}");
-            ui.horizontal(|ui| {
-                display(
-                    ui,
-                    Ctx {
-                        node: &self.path,
-                        actions: Some(&tx),
-                    },
-                    &value,
-                    String::new(),
-                );
-            });
+            match (&self.compare, other, &self.pinned) {
+                (Some(path), Some(other), _) => {
+                    let other = self.filter_props(other);
+                    show_comparison(ui, (&self.path, &shown), (path, &other));
+                }
+                (_, _, Some(pinned)) => {
+                    let changed = changed_keys(pinned, &value, "");
+                    let pinned = self.filter_props(pinned);
+                    ui.columns(2, |columns| {
+                        columns[0].label(RichText::new("Pinned").strong());
+                        display(
+                            &mut columns[0],
+                            Ctx {
+                                node: &self.path,
+                                actions: None,
+                                nav: None,
+                                changed: Some(&changed),
+                                sparks: None,
+                                breakpoints: None,
+                            },
+                            &pinned,
+                            String::new(),
+                        );
+                        columns[1].label(RichText::new("Live").strong());
+                        display(
+                            &mut columns[1],
+                            Ctx {
+                                node: &self.path,
+                                actions: Some(&tx),
+                                nav: Some(&self.nav),
+                                changed: Some(&changed),
+                                sparks: Some(&self.sparks),
+                                breakpoints: Some(breakpoints),
+                            },
+                            &shown,
+                            String::new(),
+                        );
+                    });
+                }
+                (_, _, None) => {
+                    ui.horizontal(|ui| {
+                        display(
+                            ui,
+                            Ctx {
+                                node: &self.path,
+                                actions: Some(&tx),
+                                nav: Some(&self.nav),
+                                changed: None,
+                                sparks: Some(&self.sparks),
+                                breakpoints: Some(breakpoints),
+                            },
+                            &shown,
+                            String::new(),
+                        );
+                    });
+                }
+            }
+
+            if !gates.is_empty() {
+                ui.separator();
+                show_gates(ui, gates, &tx);
+            }
 
             ui.separator();
 
             let row_height = ui.text_style_height(&TextStyle::Body);
 
+            self.filter_regex();
+            let regex = self.compiled.as_ref().filter(|_| self.regex);
+
             let stream = self.logs.streams.lock().unwrap();
             if let Some(log) = stream.get(&self.path) {
-                let matching_events = log
+                let mut matching_events = log
                     .output()
                     .into_iter()
-                    .filter(|v| v.matches(&self.filter))
+                    .filter(|v| {
+                        let level = v.metadata.level();
+                        LEVELS
+                            .iter()
+                            .position(|l| l == level)
+                            .is_some_and(|i| self.levels[i])
+                    })
+                    .filter(|v| match regex {
+                        _ if !self.scope.logs() => true,
+                        Some((_, Ok(regex))) => v.matches_regex(regex),
+                        // An invalid pattern filters nothing until fixed
+                        Some((_, Err(_))) => true,
+                        None => v.matches(&self.filter),
+                    })
                     .collect::<Vec<_>>();
 
-                TableBuilder::new(ui)
+                if self.group_spans {
+                    ScrollArea::vertical().show(ui, |ui| {
+                        SpanTree::new(&matching_events).show(ui, &colors, self.path.as_str());
+                    });
+                    return;
+                }
+
+                let names = matching_events
+                    .iter()
+                    .flat_map(|event| event.values.iter().map(|(name, _)| *name))
+                    .collect::<BTreeSet<_>>();
+                ui.menu_button(format!("Columns ({})", self.columns.len()), |ui| {
+                    if names.is_empty() {
+                        ui.weak("No structured fields logged");
+                    }
+                    for name in names {
+                        let mut shown = self.columns.iter().any(|c| c == name);
+                        if ui.checkbox(&mut shown, name).changed() {
+                            match shown {
+                                true => self.columns.push(name.to_string()),
+                                false => self.columns.retain(|c| c != name),
+                            }
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Show fields of the logs as columns of their own");
+                if self
+                    .sort
+                    .as_ref()
+                    .is_some_and(|(name, _)| !self.columns.contains(name))
+                {
+                    self.sort = None;
+                }
+                if let Some((name, descending)) = &self.sort {
+                    matching_events.sort_by(|l, r| {
+                        let ord = compare_fields(l.field(name), r.field(name));
+                        match descending {
+                            true => ord.reverse(),
+                            false => ord,
+                        }
+                    });
+                }
+
+                // Positive when scrolling up, towards older logs
+                let scrolled = ui.input(|i| i.smooth_scroll_delta.y);
+
+                let mut table = TableBuilder::new(ui)
                     .column(Column::initial(100.0).clip(true).resizable(true))
                     .column(Column::initial(100.0).clip(true).resizable(true))
                     .column(Column::initial(100.0).clip(true).resizable(true))
+                    .columns(
+                        Column::initial(80.0).clip(true).resizable(true),
+                        self.columns.len(),
+                    )
                     .column(Column::remainder().at_least(50.0))
-                    .stick_to_bottom(true)
+                    .stick_to_bottom(self.follow);
+                if jump && !matching_events.is_empty() {
+                    table = table.scroll_to_row(matching_events.len() - 1, Some(Align::BOTTOM));
+                }
+                let columns = self.columns.clone();
+                let output = table
+                    .header(row_height, |mut header| {
+                        for title in ["time", "target", "span"] {
+                            header.col(|ui| {
+                                ui.strong(title);
+                            });
+                        }
+                        for name in &columns {
+                            header.col(|ui| {
+                                let arrow = match &self.sort {
+                                    Some((sorted, false)) if sorted == name => " ⏶",
+                                    Some((sorted, true)) if sorted == name => " ⏷",
+                                    _ => "",
+                                };
+                                if ui
+                                    .button(RichText::new(format!("{name}{arrow}")).strong())
+                                    .on_hover_text("Sort by this field, numerically if possible")
+                                    .clicked()
+                                {
+                                    self.sort = match self.sort.take() {
+                                        Some((sorted, false)) if sorted == *name => {
+                                            Some((sorted, true))
+                                        }
+                                        Some((sorted, true)) if sorted == *name => None,
+                                        _ => Some((name.clone(), false)),
+                                    };
+                                }
+                            });
+                        }
+                        header.col(|ui| {
+                            ui.strong("fields");
+                        });
+                    })
                     .body(|body| {
                         body.rows(row_height, matching_events.len(), |mut row| {
                             let event = matching_events[row.index()];
                             row.col(|ui| {
                                 ui.label(
                                     RichText::new(event.time.to_string())
-                                        .color(color_for_log(*event.metadata.level())),
+                                        .color(colors.get(*event.metadata.level())),
                                 );
                             });
                             row.col(|ui| {
@@ -148,22 +681,130 @@ impl ModuleInspector {
                                     }
                                 };
                             });
-                            row.col(|ui| {
-                                ui.add(
-                                    Label::new(
-                                        RichText::new(&event.fields)
+                            for name in &columns {
+                                row.col(|ui| {
+                                    ui.label(
+                                        RichText::new(event.field(name).unwrap_or_default())
                                             .text_style(TextStyle::Monospace),
-                                    )
-                                    .wrap(),
-                                );
+                                    );
+                                });
+                            }
+                            row.col(|ui| {
+                                let segments = link_segments(&event.fields, &self.nodes);
+                                if segments.len() == 1 {
+                                    ui.add(
+                                        Label::new(
+                                            RichText::new(&event.fields)
+                                                .text_style(TextStyle::Monospace),
+                                        )
+                                        .wrap(),
+                                    );
+                                    return;
+                                }
+
+                                ui.horizontal(|ui| {
+                                    ui.spacing_mut().item_spacing.x = 0.0;
+                                    for (text, link) in segments {
+                                        let text =
+                                            RichText::new(text).text_style(TextStyle::Monospace);
+                                        match link {
+                                            Some(path) => {
+                                                if ui
+                                                    .link(text)
+                                                    .on_hover_text("Open the module inspector")
+                                                    .clicked()
+                                                {
+                                                    tx.send(ActionReq::OpenInspector(path.clone()))
+                                                        .expect("failed to send");
+                                                }
+                                            }
+                                            None => {
+                                                ui.label(text);
+                                            }
+                                        }
+                                    }
+                                });
                             });
                         });
                     });
+
+                // Scrolling away stops following, scrolling back to the
+                // bottom resumes it.
+                if scrolled != 0.0 && ui.rect_contains_pointer(output.inner_rect) {
+                    let bottom = output.state.offset.y + output.inner_rect.height();
+                    if scrolled > 0.0 {
+                        self.follow = false;
+                    } else if bottom >= output.content_size.y - row_height {
+                        self.follow = true;
+                    }
+                }
             }
         });
     }
 }
 
+/// Orders field values numerically if both are numbers, else as text.
+/// Missing values come first.
+fn compare_fields(l: Option<&str>, r: Option<&str>) -> Ordering {
+    match (l, r) {
+        (Some(l), Some(r)) => match (l.parse::<f64>(), r.parse::<f64>()) {
+            (Ok(l), Ok(r)) => l.total_cmp(&r),
+            _ => l.cmp(r),
+        },
+        (l, r) => l.is_some().cmp(&r.is_some()),
+    }
+}
+
+/// Splits `text` into plain segments and mentions of `nodes`, preferring
+/// the longest match. A mention must not be part of a longer word or path.
+fn link_segments<'a>(
+    text: &'a str,
+    nodes: &'a [ObjectPath],
+) -> Vec<(&'a str, Option<&'a ObjectPath>)> {
+    let is_path_char = |c: char| c.is_alphanumeric() || c == '_' || c == '.';
+
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let bounded_before = text[..i]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !is_path_char(c));
+        let mention = bounded_before
+            .then(|| {
+                nodes
+                    .iter()
+                    .filter(|node| {
+                        text[i..].starts_with(node.as_str())
+                            && text[i + node.as_str().len()..]
+                                .chars()
+                                .next()
+                                .is_none_or(|c| !is_path_char(c))
+                    })
+                    .max_by_key(|node| node.as_str().len())
+            })
+            .flatten();
+
+        match mention {
+            Some(node) if !node.as_str().is_empty() => {
+                if start < i {
+                    segments.push((&text[start..i], None));
+                }
+                let end = i + node.as_str().len();
+                segments.push((&text[i..end], Some(node)));
+                start = end;
+                i = end;
+            }
+            _ => i += text[i..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    if start < text.len() || segments.is_empty() {
+        segments.push((&text[start..], None));
+    }
+    segments
+}
+
 pub fn unify(props: &[(&str, Cow<Value>)]) -> Mapping {
     if props.len() == 1 {
         return Mapping::from_iter([(
@@ -248,6 +889,19 @@ fn determine_layout_constraints(value: &Value) -> LayoutConstraint {
 pub struct Ctx<'a> {
     pub node: &'a ObjectPath,
     pub actions: Option<&'a Sender<ActionReq>>,
+    pub nav: Option<&'a Nav>,
+    /// Row keys that differ from the pinned value, if one is pinned.
+    pub changed: Option<&'a FxHashSet<String>>,
+    /// The recent values of numeric keys, drawn as sparklines.
+    pub sparks: Option<&'a Sparklines>,
+    /// Row keys with a breakpoint, whose button is highlighted.
+    pub breakpoints: Option<&'a FxHashSet<String>>,
+}
+
+impl Ctx<'_> {
+    fn has_breakpoint(&self, row_key: &str) -> bool {
+        self.breakpoints.is_some_and(|b| b.contains(row_key))
+    }
 }
 
 pub fn display(ui: &mut egui::Ui, ctx: Ctx, value: &Value, key: String) {
@@ -261,17 +915,53 @@ pub fn display(ui: &mut egui::Ui, ctx: Ctx, value: &Value, key: String) {
                     let layout = determine_layout_constraints(v);
                     let k = k.as_str().unwrap();
 
+                    let row_key = format!("{key}.{k}").trim_matches('.').to_string();
+                    let focused = ctx.nav.is_some_and(|nav| nav.is_focused(&row_key));
+                    let command = ctx.nav.and_then(|nav| nav.command).filter(|_| focused);
+                    if let Some(nav) = ctx.nav {
+                        nav.rows.borrow_mut().push(row_key.clone());
+                    }
+                    if command == Some(NavCommand::AddBreakpoint)
+                        && let Some(actions) = ctx.actions
+                    {
+                        actions
+                            .send(ActionReq::Breakpoint((
+                                ctx.node.clone(),
+                                row_key.clone(),
+                                Some(v.clone()),
+                            )))
+                            .expect("failed to send");
+                    }
+                    let changed = ctx.changed.is_some_and(|c| c.contains(&row_key));
+                    let label = |text: String| {
+                        let text = match focused {
+                            true => RichText::new(text).background_color(Color32::DARK_BLUE),
+                            false => RichText::new(text),
+                        };
+                        match changed {
+                            true => text.color(Color32::YELLOW),
+                            false => text,
+                        }
+                    };
+
                     match layout {
                         LayoutConstraint::Shallow => {
                             ui.horizontal(|ui| {
-                                ui.label(format!("{}:", k));
+                                ui.add(Label::new(label(format!("{}:", k))).sense(Sense::click()))
+                                    .on_hover_text(key_hover(&row_key))
+                                    .context_menu(|ui| copy_menu(ui, v));
                                 display(ui, ctx, &v, format!("{key}.{k}"));
                             });
                         }
                         LayoutConstraint::Deep => {
-                            let id = ui.make_persistent_id((&key, k));
+                            let id = collapsing_id(ctx.node, &row_key);
                             let mut state =
                                 CollapsingState::load_with_default_open(&ui.ctx(), id, false);
+                            match command {
+                                Some(NavCommand::Expand) => state.set_open(true),
+                                Some(NavCommand::Collapse) => state.set_open(false),
+                                _ => {}
+                            }
 
                             let id_toggle = ui.make_persistent_id((id, "toggle"));
                             let should_toggle: bool =
@@ -287,12 +977,16 @@ pub fn display(ui: &mut egui::Ui, ctx: Ctx, value: &Value, key: String) {
 
                             state
                                 .show_header(ui, |ui| {
-                                    let resp = ui.vertical(|ui| ui.label(k));
+                                    let resp = ui.vertical(|ui| ui.label(label(k.to_string())));
                                     let id_interact = ui.make_persistent_id((id, "interact"));
-                                    if ui
-                                        .interact(resp.response.rect, id_interact, Sense::click())
-                                        .clicked()
-                                    {
+                                    let header = ui.interact(
+                                        resp.response.rect,
+                                        id_interact,
+                                        Sense::click(),
+                                    );
+                                    let header = header.on_hover_text(key_hover(&row_key));
+                                    header.context_menu(|ui| copy_menu(ui, v));
+                                    if header.clicked() {
                                         ui.memory_mut(|m| {
                                             let should_toggle =
                                                 m.data.get_temp_mut_or_default::<bool>(id_toggle);
@@ -301,23 +995,26 @@ pub fn display(ui: &mut egui::Ui, ctx: Ctx, value: &Value, key: String) {
                                     }
 
                                     if let Some(actions) = ctx.actions {
+                                        let set = ctx.has_breakpoint(&row_key);
                                         let btn = Button::image(egui::Image::new(
                                             egui::include_image!("../../assets/breakpoint.png"),
                                         ))
                                         .corner_radius(5.0)
-                                        .frame(false);
+                                        .frame(false)
+                                        .selected(set);
 
-                                        if ui.add(btn).clicked() {
+                                        if ui.add(btn).on_hover_text(BREAKPOINT_HINT).clicked() {
                                             actions
                                                 .send(ActionReq::Breakpoint((
                                                     ctx.node.clone(),
-                                                    format!("{key}.{k}")
-                                                        .trim_matches('.')
-                                                        .to_string(),
-                                                    Some(value.clone()),
+                                                    row_key.clone(),
+                                                    Some(v.clone()),
                                                 )))
                                                 .expect("failed to send");
                                         }
+                                        if v.is_mapping() {
+                                            observe_all(ui, ctx.node, v, &row_key, actions);
+                                        }
                                     }
                                 })
                                 .body(|ui| {
@@ -335,6 +1032,17 @@ pub fn display(ui: &mut egui::Ui, ctx: Ctx, value: &Value, key: String) {
             ui.label("[]");
         }
         Value::Sequence(seq) => {
+            if let Some(actions) = ctx.actions
+                && seq.iter().all(Value::is_number)
+                && ui.button("Observe").clicked()
+            {
+                actions
+                    .send(ActionReq::TraceSequence((
+                        ctx.node.clone(),
+                        key.trim_matches('.').to_string(),
+                    )))
+                    .expect("failed to send");
+            }
             ui.vertical(|ui| {
                 for (i, v) in seq.iter().enumerate() {
                     display(ui, ctx, &v, format!("{key}.{i}"));
@@ -348,18 +1056,32 @@ pub fn display(ui: &mut egui::Ui, ctx: Ctx, value: &Value, key: String) {
 
         Value::Tagged(tagged) => {
             ui.horizontal(|ui| {
-                CollapsingHeader::new(tagged.tag.to_string().trim_start_matches('!'))
-                    .default_open(true)
-                    .show(ui, |ui| display(ui, ctx, &tagged.value, key.clone()))
+                variant_badge(ui, tagged.tag.to_string().trim_start_matches('!'));
+                display(ui, ctx, &tagged.value, key.clone());
             });
             return;
         }
 
         Value::String(s) => {
-            ui.label(s);
+            editable(ui, ctx, value, &key, s.clone());
+            watch_button(ui, ctx, &key);
         }
         Value::Number(n) => {
-            ui.label(n.to_string());
+            editable(ui, ctx, value, &key, n.to_string());
+            if let Some(samples) = ctx.sparks.and_then(|s| s.get(key.trim_matches('.')))
+                && samples.len() > 1
+                && let Some(actions) = ctx.actions
+                && sparkline(ui, samples)
+                    .on_hover_text("Recent values, click to plot")
+                    .clicked()
+            {
+                actions
+                    .send(ActionReq::Trace((
+                        ctx.node.clone(),
+                        key.trim_matches('.').to_string(),
+                    )))
+                    .expect("failed to send");
+            }
             if let Some(actions) = ctx.actions {
                 if ui.button("Observe").clicked() {
                     actions
@@ -369,6 +1091,10 @@ pub fn display(ui: &mut egui::Ui, ctx: Ctx, value: &Value, key: String) {
                         )))
                         .expect("failed to send");
                 }
+                observe_vs(ui, ctx.node, key.trim_matches('.'), actions);
+                if !is_channel_path(ctx.node) {
+                    observe_across(ui, ctx.node, key.trim_matches('.'), actions);
+                }
             }
         }
         Value::Null => {
@@ -376,21 +1102,25 @@ pub fn display(ui: &mut egui::Ui, ctx: Ctx, value: &Value, key: String) {
         }
         Value::Bool(b) => {
             ui.label(b.to_string());
+            watch_button(ui, ctx, &key);
         }
     }
 
     if let Some(actions) = ctx.actions {
+        let key = key.trim_matches('.').to_string();
+        let set = ctx.has_breakpoint(&key);
         let btn = Button::image(egui::Image::new(egui::include_image!(
             "../../assets/breakpoint.png"
         )))
         .corner_radius(5.0)
-        .frame(false);
+        .frame(false)
+        .selected(set);
 
-        if ui.add(btn).clicked() {
+        if ui.add(btn).on_hover_text(BREAKPOINT_HINT).clicked() {
             actions
                 .send(ActionReq::Breakpoint((
                     ctx.node.clone(),
-                    key.trim_matches('.').to_string(),
+                    key,
                     Some(value.clone()),
                 )))
                 .expect("failed to send");
@@ -398,12 +1128,636 @@ pub fn display(ui: &mut egui::Ui, ctx: Ctx, value: &Value, key: String) {
     }
 }
 
-fn color_for_log(level: Level) -> Color32 {
-    match level {
-        Level::TRACE => Color32::from_rgb(0, 128, 0),
-        Level::DEBUG => Color32::from_rgb(0, 0, 255),
-        Level::INFO => Color32::from_rgb(0, 255, 0),
-        Level::WARN => Color32::from_rgb(255, 255, 0),
-        Level::ERROR => Color32::from_rgb(255, 0, 0),
+/// The full prop key of a row, as used by breakpoints and traces. Keys
+/// shown in the tree may be abbreviated by `unify`.
+fn key_hover(row_key: &str) -> RichText {
+    RichText::new(row_key).monospace()
+}
+
+/// The id of the collapsing state of a mapping row. It does not depend on
+/// the surrounding ui, so that [`set_all_open`] can find it.
+fn collapsing_id(node: &ObjectPath, row_key: &str) -> egui::Id {
+    egui::Id::new(("collapsing", node, row_key))
+}
+
+/// Opens or closes all collapsible rows of `value`, including those nested
+/// in closed rows, which `display` does not visit.
+fn set_all_open(ctx: &egui::Context, node: &ObjectPath, value: &Value, prefix: &str, open: bool) {
+    let key = |k: &dyn std::fmt::Display| format!("{prefix}.{k}").trim_matches('.').to_string();
+    match value {
+        Value::Mapping(map) => {
+            for (k, v) in map {
+                let Some(k) = k.as_str() else { continue };
+                let row_key = key(&k);
+                if matches!(determine_layout_constraints(v), LayoutConstraint::Deep) {
+                    let id = collapsing_id(node, &row_key);
+                    let mut state = CollapsingState::load_with_default_open(ctx, id, false);
+                    state.set_open(open);
+                    state.store(ctx);
+                }
+                set_all_open(ctx, node, v, &row_key, open);
+            }
+        }
+        Value::Sequence(seq) => {
+            for (i, v) in seq.iter().enumerate() {
+                set_all_open(ctx, node, v, &key(&i), open);
+            }
+        }
+        Value::Tagged(tagged) => set_all_open(ctx, node, &tagged.value, prefix, open),
+        _ => {}
+    }
+}
+
+/// The scalar leaves of `value` by their row key, as built by `display`.
+fn flatten(value: &Value, prefix: &str, out: &mut BTreeMap<String, String>) {
+    let key = |k: &dyn std::fmt::Display| format!("{prefix}.{k}").trim_matches('.').to_string();
+    match value {
+        Value::Mapping(map) if !map.is_empty() => {
+            for (k, v) in map {
+                let k = k.as_str().map_or_else(|| copy_text(k), str::to_string);
+                flatten(v, &key(&k), out);
+            }
+        }
+        Value::Sequence(seq) if !seq.is_empty() => {
+            for (i, v) in seq.iter().enumerate() {
+                flatten(v, &key(&i), out);
+            }
+        }
+        Value::Tagged(tagged) => flatten(&tagged.value, prefix, out),
+        Value::Mapping(_) => {
+            out.insert(prefix.to_string(), "[:]".to_string());
+        }
+        Value::Sequence(_) => {
+            out.insert(prefix.to_string(), "[]".to_string());
+        }
+        scalar => {
+            out.insert(prefix.to_string(), copy_text(scalar));
+        }
+    }
+}
+
+/// The leaves of both values aligned by key, `None` where a key exists on
+/// one side only.
+fn align(left: &Value, right: &Value) -> Vec<(String, Option<String>, Option<String>)> {
+    let (mut l, mut r) = (BTreeMap::new(), BTreeMap::new());
+    flatten(left, "", &mut l);
+    flatten(right, "", &mut r);
+
+    let mut keys = l.keys().chain(r.keys()).cloned().collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .map(|k| {
+            let (lv, rv) = (l.remove(&k), r.remove(&k));
+            (k, lv, rv)
+        })
+        .collect()
+}
+
+/// Two modules side by side, differing values are highlighted and keys
+/// that exist on one side only are marked.
+fn show_comparison(ui: &mut egui::Ui, left: (&ObjectPath, &Value), right: (&ObjectPath, &Value)) {
+    let missing = || RichText::new("missing").italics().color(Color32::LIGHT_RED);
+    Grid::new(("compare", left.0, right.0))
+        .striped(true)
+        .show(ui, |ui| {
+            ui.strong("key");
+            ui.strong(left.0.as_str());
+            ui.strong(right.0.as_str());
+            ui.end_row();
+
+            for (key, l, r) in align(left.1, right.1) {
+                let differs = l != r;
+                ui.label(match (differs, l.is_some() && r.is_some()) {
+                    (false, _) => RichText::new(key),
+                    (true, true) => RichText::new(key).color(Color32::YELLOW),
+                    (true, false) => RichText::new(key).color(Color32::LIGHT_RED),
+                });
+                for side in [l, r] {
+                    match side {
+                        Some(text) if differs => {
+                            ui.label(RichText::new(text).color(Color32::YELLOW))
+                        }
+                        Some(text) => ui.label(text),
+                        None => ui.label(missing()),
+                    };
+                }
+                ui.end_row();
+            }
+        });
+}
+
+/// The row keys, as built by `display`, of all entries that were added,
+/// removed or changed between `pinned` and `live`. Parents of a changed
+/// entry are included, so that collapsed sections are highlighted too.
+fn changed_keys(pinned: &Value, live: &Value, prefix: &str) -> FxHashSet<String> {
+    let mut changed = FxHashSet::default();
+    let (Value::Mapping(pinned), Value::Mapping(live)) = (pinned, live) else {
+        return changed;
+    };
+
+    let keys = pinned
+        .keys()
+        .chain(live.keys().filter(|k| !pinned.contains_key(*k)));
+    for k in keys {
+        let Some(name) = k.as_str() else {
+            continue;
+        };
+        let (old, new) = (pinned.get(k), live.get(k));
+        if old == new {
+            continue;
+        }
+        let row_key = format!("{prefix}.{name}").trim_matches('.').to_string();
+        if let (Some(old), Some(new)) = (old, new) {
+            changed.extend(changed_keys(old, new, &row_key));
+        }
+        changed.insert(row_key);
+    }
+    changed
+}
+
+/// The connected gates of a module, with the configured metrics and the
+/// utilization of each channel.
+fn show_gates(ui: &mut egui::Ui, gates: &[GateInfo], tx: &Sender<ActionReq>) {
+    CollapsingHeader::new(format!("Gates ({})", gates.len()))
+        .default_open(false)
+        .show(ui, |ui| {
+            for gate in gates {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(&gate.name).strong());
+                    match gate.incoming {
+                        true => ui.label(format!("<- {}", gate.peer)),
+                        false => ui.label(format!("-> {}", gate.peer)),
+                    };
+                });
+                let Some((path, metrics)) = &gate.channel else {
+                    ui.label(RichText::new("No channel").weak());
+                    continue;
+                };
+                if gate.incoming {
+                    show_in_flight(ui, metrics);
+                }
+
+                ui.horizontal(|ui| {
+                    let utilization = metrics
+                        .get("utilization")
+                        .and_then(Value::as_f64)
+                        .unwrap_or(0.0);
+                    ui.add(
+                        ProgressBar::new(utilization as f32)
+                            .desired_width(120.0)
+                            .text(format!("{:.1}%", utilization * 100.0)),
+                    )
+                    .on_hover_text("Fraction of the sim time the channel was busy");
+                    if let Some(n) = metrics.get("transmissions").and_then(Value::as_u64) {
+                        ui.label(format!("{n} sent"))
+                            .on_hover_text("Transmissions started on this channel");
+                    }
+                    if ui.small_button("Open").clicked() {
+                        tx.send(ActionReq::OpenInspector(path.clone()))
+                            .expect("failed to send");
+                    }
+                });
+                display(
+                    ui,
+                    Ctx {
+                        node: path,
+                        actions: None,
+                        nav: None,
+                        changed: None,
+                        sparks: None,
+                        breakpoints: None,
+                    },
+                    &Value::Mapping(metrics.clone()),
+                    String::new(),
+                );
+            }
+        });
+}
+
+/// The message in transmission towards an incoming gate, if any.
+fn show_in_flight(ui: &mut egui::Ui, metrics: &Mapping) {
+    let get = |key: &str| metrics.get(key).and_then(Value::as_f64).unwrap_or(0.0);
+    match metrics.get("busy").and_then(Value::as_bool) {
+        Some(true) => {
+            let arrival = get("transmission_finish") + get("latency");
+            ui.label(
+                RichText::new(format!(
+                    "{} bytes in flight, arriving at ~{arrival:.6}s",
+                    get("bytes_in_flight")
+                ))
+                .color(Color32::YELLOW),
+            )
+            .on_hover_text("Finish of the current transmission plus the channel latency");
+        }
+        _ => {
+            ui.label(RichText::new("Nothing in flight").weak());
+        }
+    }
+}
+
+/// A context menu entry, that copies `value` to the clipboard.
+fn copy_menu(ui: &mut egui::Ui, value: &Value) {
+    if ui.button("Copy value").clicked() {
+        ui.ctx().copy_text(copy_text(value));
+        ui.close_menu();
+    }
+}
+
+/// Scalars are copied as displayed, subtrees as YAML.
+pub fn copy_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => "null".to_string(),
+        other => serde_norway::to_string(other).expect("failed to serialize value"),
+    }
+}
+
+/// The variant of an enum, colored by its name so that the variants of a
+/// state machine are told apart at a glance.
+fn variant_badge(ui: &mut egui::Ui, tag: &str) {
+    let color = TRACE_COLORS[fxhash::hash(tag) % TRACE_COLORS.len()];
+    ui.label(
+        RichText::new(tag)
+            .strong()
+            .color(Color32::BLACK)
+            .background_color(color),
+    );
+}
+
+/// Records the changes of a value that cannot be plotted, see `WatchTable`.
+fn watch_button(ui: &mut egui::Ui, ctx: Ctx, key: &str) {
+    if let Some(actions) = ctx.actions
+        && ui.button("Watch").clicked()
+    {
+        actions
+            .send(ActionReq::Watch((
+                ctx.node.clone(),
+                key.trim_matches('.').to_string(),
+            )))
+            .expect("failed to send");
+    }
+}
+
+/// A menu to plot a key combined over the module and all its siblings.
+fn observe_across(ui: &mut egui::Ui, node: &ObjectPath, key: &str, tx: &Sender<ActionReq>) {
+    ui.menu_button("Across siblings", |ui| {
+        for combine in [Combine::Sum, Combine::Mean, Combine::Max] {
+            if ui.button(combine.to_string()).clicked() {
+                tx.send(ActionReq::TraceAcross((
+                    (node.clone(), key.to_string()),
+                    combine,
+                )))
+                .expect("failed to send");
+                ui.close_menu();
+            }
+        }
+    })
+    .response
+    .on_hover_text(format!(
+        "Plot {key} of all modules matching {}",
+        siblings_pattern(node)
+    ));
+}
+
+/// Above this many traces, "Observe all" asks for confirmation.
+const OBSERVE_ALL_CONFIRM: usize = 20;
+
+/// Traces all numeric leaves below a mapping, each as its own trace.
+fn observe_all(
+    ui: &mut egui::Ui,
+    node: &ObjectPath,
+    value: &Value,
+    key: &str,
+    actions: &Sender<ActionReq>,
+) {
+    let leaves = numeric_leaves(value, key);
+    if leaves.is_empty() {
+        return;
+    }
+    let id = egui::Id::new(("observe-all", node, key));
+    let send = |leaves: Vec<String>| {
+        for leaf in leaves {
+            actions
+                .send(ActionReq::Trace((node.clone(), leaf)))
+                .expect("failed to send");
+        }
+    };
+
+    if ui
+        .small_button(format!("Observe all ({})", leaves.len()))
+        .on_hover_text("Plot each numeric value below as its own trace")
+        .clicked()
+    {
+        if leaves.len() > OBSERVE_ALL_CONFIRM {
+            ui.ctx().data_mut(|d| d.insert_temp(id, true));
+        } else {
+            send(leaves);
+            return;
+        }
+    }
+
+    if ui.ctx().data(|d| d.get_temp::<bool>(id)).unwrap_or(false) {
+        let modal = Modal::new(id.with("modal")).show(ui.ctx(), |ui| {
+            ui.label(format!("Add {} traces for {node} {key}?", leaves.len()));
+            ui.horizontal(|ui| (ui.button("Add").clicked(), ui.button("Cancel").clicked()))
+                .inner
+        });
+        let (add, cancel) = modal.inner;
+        if add || cancel || modal.should_close() {
+            ui.ctx().data_mut(|d| d.remove::<bool>(id));
+        }
+        if add {
+            send(leaves);
+        }
+    }
+}
+
+/// The branches of `value` whose row key or leaf value matches, with the
+/// branches below matching keys kept whole. Sequences are kept whole if any
+/// element matches, so that the row keys of the elements stay the same.
+fn prune(value: &Value, prefix: &str, matches: &dyn Fn(&str) -> bool) -> Option<Value> {
+    let key = |k: &dyn std::fmt::Display| format!("{prefix}.{k}").trim_matches('.').to_string();
+    match value {
+        Value::Mapping(map) => {
+            let pruned = map
+                .iter()
+                .filter_map(|(k, v)| {
+                    let row_key = key(&k.as_str()?);
+                    match matches(&row_key) {
+                        true => Some((k.clone(), v.clone())),
+                        false => Some((k.clone(), prune(v, &row_key, matches)?)),
+                    }
+                })
+                .collect::<Mapping>();
+            (!pruned.is_empty()).then_some(Value::Mapping(pruned))
+        }
+        Value::Sequence(seq) => seq
+            .iter()
+            .enumerate()
+            .any(|(i, v)| prune(v, &key(&i), matches).is_some())
+            .then(|| value.clone()),
+        Value::Tagged(tagged) => prune(&tagged.value, prefix, matches).map(|_| value.clone()),
+        leaf => matches(&copy_text(leaf)).then(|| leaf.clone()),
+    }
+}
+
+/// The row keys of all numeric leaves of `value`.
+fn numeric_leaves(value: &Value, prefix: &str) -> Vec<String> {
+    let key = |k: &dyn std::fmt::Display| format!("{prefix}.{k}").trim_matches('.').to_string();
+    match value {
+        Value::Mapping(map) => map
+            .iter()
+            .filter_map(|(k, v)| Some(numeric_leaves(v, &key(&k.as_str()?))))
+            .flatten()
+            .collect(),
+        Value::Sequence(seq) => seq
+            .iter()
+            .enumerate()
+            .flat_map(|(i, v)| numeric_leaves(v, &key(&i)))
+            .collect(),
+        Value::Tagged(tagged) => numeric_leaves(&tagged.value, prefix),
+        Value::Number(_) => vec![prefix.to_string()],
+        _ => Vec::new(),
+    }
+}
+
+/// The two-step selection of a scatter plot. The first click picks the x
+/// axis, which is kept in memory so that any other inspector can pick the y
+/// axis. Clicking the x axis again cancels the selection.
+fn observe_vs(ui: &mut egui::Ui, node: &ObjectPath, key: &str, actions: &Sender<ActionReq>) {
+    let id = egui::Id::new("observe-vs");
+    let current = (node.clone(), key.to_string());
+    match ui.ctx().data(|d| d.get_temp::<TreeTraceReq>(id)) {
+        Some(x) if x == current => {
+            if ui
+                .button(RichText::new("Cancel vs…").color(Color32::YELLOW))
+                .clicked()
+            {
+                ui.ctx().data_mut(|d| d.remove::<TreeTraceReq>(id));
+            }
+        }
+        Some(x) => {
+            if ui
+                .button(format!("vs {} {}", x.0, x.1))
+                .on_hover_text("Plot this value against the selected one")
+                .clicked()
+            {
+                actions
+                    .send(ActionReq::TraceVs((x, current)))
+                    .expect("failed to send");
+                ui.ctx().data_mut(|d| d.remove::<TreeTraceReq>(id));
+            }
+        }
+        None => {
+            if ui
+                .button("Observe vs…")
+                .on_hover_text("Select as x axis, then pick a value to plot against it")
+                .clicked()
+            {
+                ui.ctx().data_mut(|d| d.insert_temp(id, current));
+            }
+        }
+    }
+}
+
+/// A scalar label with an "Edit" button, that turns into a text field.
+/// Committing with Enter sends a `SetProp` request, any other loss of focus
+/// cancels the edit.
+fn editable(ui: &mut egui::Ui, ctx: Ctx, value: &Value, key: &str, text: String) {
+    let Some(actions) = ctx.actions else {
+        ui.label(text);
+        return;
+    };
+
+    let id = ui.make_persistent_id((ctx.node, key, "edit"));
+    let text_id = id.with("text");
+    let Some(mut buffer) = ui.memory(|m| m.data.get_temp::<String>(id)) else {
+        ui.label(&text);
+        if ui.small_button("Edit").clicked() {
+            ui.memory_mut(|m| {
+                m.data.insert_temp(id, text);
+                m.request_focus(text_id);
+            });
+        }
+        return;
+    };
+
+    let resp = ui.add(
+        TextEdit::singleline(&mut buffer)
+            .id(text_id)
+            .desired_width(120.0),
+    );
+    if resp.lost_focus() {
+        let parsed = match value {
+            Value::String(_) => Some(Value::String(buffer)),
+            _ => serde_norway::from_str::<Value>(&buffer)
+                .ok()
+                .filter(Value::is_number),
+        };
+        if ui.input(|i| i.key_pressed(Key::Enter))
+            && let Some(parsed) = parsed
+        {
+            actions
+                .send(ActionReq::SetProp((
+                    ctx.node.clone(),
+                    key.trim_matches('.').to_string(),
+                    parsed,
+                )))
+                .expect("failed to send");
+        }
+        ui.memory_mut(|m| m.data.remove::<String>(id));
+    } else {
+        ui.memory_mut(|m| m.data.insert_temp(id, buffer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_known_paths() {
+        let nodes = [ObjectPath::from("pong"), ObjectPath::from("pong.eth")];
+        let segments = link_segments("sent to pong.eth, not pongs", &nodes);
+        assert_eq!(
+            segments
+                .iter()
+                .map(|(text, link)| (*text, link.is_some()))
+                .collect::<Vec<_>>(),
+            [
+                ("sent to ", false),
+                ("pong.eth", true),
+                (", not pongs", false)
+            ]
+        );
+
+        assert_eq!(link_segments("nothing", &nodes).len(), 1);
+    }
+
+    #[test]
+    fn diffs_against_pinned_value() {
+        let value = |counter: u64| {
+            Value::Mapping(Mapping::from_iter([
+                (Value::from("name"), Value::from("ping")),
+                (
+                    Value::from("stats"),
+                    Value::Mapping(Mapping::from_iter([(
+                        Value::from("counter"),
+                        Value::from(counter),
+                    )])),
+                ),
+            ]))
+        };
+
+        let changed = changed_keys(&value(1), &value(2), "");
+        let mut changed = changed.into_iter().collect::<Vec<_>>();
+        changed.sort();
+        assert_eq!(changed, ["stats", "stats.counter"]);
+        assert!(changed_keys(&value(1), &value(1), "").is_empty());
+    }
+
+    #[test]
+    fn aligns_by_key() {
+        let left = serde_norway::from_str::<Value>("{ a: 1, b: { c: x } }").unwrap();
+        let right = serde_norway::from_str::<Value>("{ a: 2, d: [] }").unwrap();
+        let some = |s: &str| Some(s.to_string());
+        assert_eq!(
+            align(&left, &right),
+            [
+                ("a".to_string(), some("1"), some("2")),
+                ("b.c".to_string(), some("x"), None),
+                ("d".to_string(), None, some("[]")),
+            ]
+        );
+    }
+
+    #[test]
+    fn expands_nested_rows() {
+        let ctx = egui::Context::default();
+        let node = ObjectPath::from("ping");
+        let value = serde_norway::from_str::<Value>("{ a: { b: { c: 1 } }, d: 1 }").unwrap();
+        let is_open =
+            |key: &str| CollapsingState::load(&ctx, collapsing_id(&node, key)).map(|s| s.is_open());
+
+        set_all_open(&ctx, &node, &value, "", true);
+        assert_eq!(is_open("a"), Some(true));
+        assert_eq!(is_open("a.b"), Some(true));
+        assert_eq!(is_open("d"), None);
+
+        set_all_open(&ctx, &node, &value, "", false);
+        assert_eq!(is_open("a.b"), Some(false));
+    }
+
+    #[test]
+    fn finds_numeric_leaves() {
+        let value =
+            serde_norway::from_str::<Value>("{ a: 1, b: { c: 2.5, d: x }, e: [3] }").unwrap();
+        assert_eq!(numeric_leaves(&value, "m"), ["m.a", "m.b.c", "m.e.0"]);
+    }
+
+    #[test]
+    fn copies_subtrees_as_yaml() {
+        let value = Value::Mapping(Mapping::from_iter([(
+            Value::String("counter".to_string()),
+            Value::from(3),
+        )]));
+        assert_eq!(copy_text(&value), "counter: 3\n");
+        assert_eq!(copy_text(&Value::String("ping".to_string())), "ping");
+    }
+
+    #[test]
+    fn sorts_fields_numerically() {
+        let mut values = vec![Some("10"), None, Some("9"), Some("b"), Some("a")];
+        values.sort_by(|l, r| compare_fields(*l, *r));
+        assert_eq!(values, [None, Some("9"), Some("10"), Some("a"), Some("b")]);
+    }
+
+    #[test]
+    fn dumps_state() {
+        let value =
+            serde_norway::from_str::<Value>("{ counter: 3, peer: { name: pong } }").unwrap();
+
+        let mut out = Vec::new();
+        StateFormat::Yaml.write(&mut out, &value).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "counter: 3\npeer:\n  name: pong\n"
+        );
+
+        let mut out = Vec::new();
+        StateFormat::Json.write(&mut out, &value).unwrap();
+        let json = serde_json::from_slice::<serde_json::Value>(&out).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "counter": 3, "peer": { "name": "pong" } })
+        );
+    }
+
+    #[test]
+    fn prunes_to_matching_branches() {
+        let value = serde_norway::from_str::<Value>(
+            "{ name: ping, stats: { sent: 3, queue: [1, 2] }, peer: { name: pong } }",
+        )
+        .unwrap();
+        let matching = |query: &str| {
+            prune(&value, "", &|s| s.contains(query)).map(|v| serde_norway::to_string(&v).unwrap())
+        };
+
+        // Keys match by their full row key, keeping what is below them
+        assert_eq!(
+            matching("stats.q").as_deref(),
+            Some("stats:\n  queue:\n  - 1\n  - 2\n")
+        );
+        // Leaves match by their value
+        assert_eq!(matching("pong").as_deref(), Some("peer:\n  name: pong\n"));
+        // Sequences are kept whole if any element matches
+        assert_eq!(
+            matching("2").as_deref(),
+            Some("stats:\n  queue:\n  - 1\n  - 2\n")
+        );
+        assert_eq!(matching("nothing"), None);
     }
 }
@@ -0,0 +1,62 @@
+use egui::{CollapsingHeader, RichText, TextStyle, Ui};
+
+use crate::tracing::{Event, LogColors};
+
+/// Log events grouped by the spans they were emitted in.
+///
+/// Spans are keyed by name and fields, so separate entries of the same
+/// span, e.g. with different ids, are separate nodes.
+#[derive(Debug, Default)]
+pub struct SpanTree<'a> {
+    events: Vec<&'a Event>,
+    // In order of the first event, like the log table
+    children: Vec<(String, SpanTree<'a>)>,
+}
+
+impl<'a> SpanTree<'a> {
+    pub fn new(events: &[&'a Event]) -> Self {
+        let mut root = Self::default();
+        for event in events {
+            let mut node = &mut root;
+            for span in event.spans() {
+                let label = span.to_string();
+                let i = match node.children.iter().position(|(l, _)| *l == label) {
+                    Some(i) => i,
+                    None => {
+                        node.children.push((label, Self::default()));
+                        node.children.len() - 1
+                    }
+                };
+                node = &mut node.children[i].1;
+            }
+            node.events.push(event);
+        }
+        root
+    }
+
+    /// The number of events in this span, including nested spans.
+    fn len(&self) -> usize {
+        self.events.len() + self.children.iter().map(|(_, c)| c.len()).sum::<usize>()
+    }
+
+    pub fn show(&self, ui: &mut Ui, colors: &LogColors, id: &str) {
+        for event in &self.events {
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(event.time.to_string())
+                        .color(colors.get(*event.metadata.level())),
+                );
+                ui.label(RichText::new(&event.fields).text_style(TextStyle::Monospace));
+            });
+        }
+        for (label, child) in &self.children {
+            let id = format!("{id}:{label}");
+            CollapsingHeader::new(
+                RichText::new(format!("{label} ({})", child.len()))
+                    .text_style(TextStyle::Monospace),
+            )
+            .id_salt(&id)
+            .show(ui, |ui| child.show(ui, colors, &id));
+        }
+    }
+}
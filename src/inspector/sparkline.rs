@@ -0,0 +1,114 @@
+use std::collections::VecDeque;
+
+use des::time::SimTime;
+use egui::{Response, Sense, Shape, Stroke, Ui, pos2, vec2};
+use fxhash::FxHashMap;
+use serde_norway::Value;
+
+/// The number of samples kept per key.
+const CAPACITY: usize = 64;
+const WIDTH: f32 = 40.0;
+
+/// The recent values of all numeric keys of an inspected module.
+///
+/// A sample is taken at most once per sim time, so a paused sim does not
+/// flatten the history.
+#[derive(Debug, Clone, Default)]
+pub struct Sparklines {
+    history: FxHashMap<String, VecDeque<f64>>,
+    last: Option<SimTime>,
+}
+
+impl Sparklines {
+    pub fn record(&mut self, value: &Value) {
+        let now = SimTime::now();
+        if self.last == Some(now) {
+            return;
+        }
+        self.last = Some(now);
+        self.record_at(value, "");
+    }
+
+    fn record_at(&mut self, value: &Value, prefix: &str) {
+        let key = |k: &dyn std::fmt::Display| format!("{prefix}.{k}").trim_matches('.').to_string();
+        match value {
+            Value::Mapping(map) => {
+                for (k, v) in map {
+                    if let Some(k) = k.as_str() {
+                        self.record_at(v, &key(&k));
+                    }
+                }
+            }
+            Value::Sequence(seq) => {
+                for (i, v) in seq.iter().enumerate() {
+                    self.record_at(v, &key(&i));
+                }
+            }
+            Value::Tagged(tagged) => self.record_at(&tagged.value, prefix),
+            Value::Number(n) => {
+                let samples = self.history.entry(prefix.to_string()).or_default();
+                if samples.len() == CAPACITY {
+                    samples.pop_front();
+                }
+                samples.push_back(n.as_f64().unwrap_or(f64::NAN));
+            }
+            _ => {}
+        }
+    }
+
+    /// The recent values of a row key, as built by `display`.
+    pub fn get(&self, key: &str) -> Option<&VecDeque<f64>> {
+        self.history.get(key)
+    }
+}
+
+/// Draws `samples` as a small line, scaled to their min and max.
+pub fn sparkline(ui: &mut Ui, samples: &VecDeque<f64>) -> Response {
+    let height = ui.text_style_height(&egui::TextStyle::Body);
+    let (resp, painter) = ui.allocate_painter(vec2(WIDTH, height), Sense::click());
+    let rect = resp.rect;
+
+    let (min, max) = samples
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), y| {
+            (min.min(*y), max.max(*y))
+        });
+    let range = (max - min).max(f64::EPSILON);
+    let step = rect.width() / (samples.len().max(2) - 1) as f32;
+    let points = samples
+        .iter()
+        .enumerate()
+        .map(|(i, y)| {
+            let y = ((y - min) / range) as f32;
+            pos2(
+                rect.left() + i as f32 * step,
+                rect.bottom() - y * rect.height(),
+            )
+        })
+        .collect();
+
+    let color = match resp.hovered() {
+        true => ui.visuals().strong_text_color(),
+        false => ui.visuals().weak_text_color(),
+    };
+    painter.add(Shape::line(points, Stroke::new(1.0, color)));
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_numeric_keys() {
+        let value = serde_norway::from_str::<Value>("{ a: 1, b: { c: 2.5 }, d: x }").unwrap();
+        let mut sparks = Sparklines::default();
+        sparks.record(&value);
+        // The same sim time is sampled once
+        sparks.record(&value);
+
+        assert_eq!(sparks.get("a"), Some(&VecDeque::from([1.0])));
+        assert_eq!(sparks.get("b.c"), Some(&VecDeque::from([2.5])));
+        assert_eq!(sparks.get("d"), None);
+    }
+}
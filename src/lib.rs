@@ -1,109 +1,305 @@
-use breakpoint::{Breakpoint, BreakpointKind};
-use des::{prelude::*, runtime::RuntimeResult, tracing::FALLBACK_LOG_LEVEL};
+pub use breakpoint::{Breakpoint, BreakpointKind};
+use channel::{ChannelObserver, is_channel_path};
+use config::{BreakpointConfig, ExecutionMode, GuiConfig, LayoutConfig, TraceConfig};
+use des::{prelude::*, runtime::RuntimeResult, time::SimTime, tracing::FALLBACK_LOG_LEVEL};
 use egui::{
-    CentralPanel, CollapsingHeader, Id, Image, RichText, ScrollArea, SidePanel, ViewportBuilder,
+    CentralPanel, CollapsingHeader, Color32, Id, Modal, RichText, ScrollArea, SidePanel, TextEdit,
+    ViewportBuilder,
 };
-use fxhash::FxHashMap;
-use petgraph::dot::{Config, Dot};
-use plot::{Tracer, TreeTracer};
+use egui_file_dialog::FileDialog;
+use fxhash::{FxHashMap, FxHashSet};
+pub use headless::run_headless;
+pub use plot::{Combine, Op};
+use plot::{
+    Marker, PlotScreenshot, SequenceTracer, TraceSnapshot, Tracer, TreeTracer, XYTracer, access,
+};
+use serde::{Deserialize, Serialize};
 use serde_norway::{Mapping, Value};
 use std::{
+    any::Any,
     borrow::Cow,
     env::{self, temp_dir, var},
-    fs::{self, File},
-    io::Write,
-    mem::{self, forget},
+    mem,
     ops::{ControlFlow, Deref, DerefMut},
-    path::{Path, PathBuf},
-    process::{Command, Stdio},
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
     sync::mpsc::{Receiver, Sender, channel},
     time::{Duration, Instant},
 };
 use tracing_error::ErrorLayer;
 use tracing_subscriber::{EnvFilter, filter::Directive, fmt::Layer, layer::SubscriberExt};
 
+pub mod config;
 pub mod sim;
 pub mod tracing;
 
+mod activity;
 mod breakpoint;
+mod channel;
 mod controls;
+mod graph;
+mod headless;
 mod inspector;
+mod log_view;
+mod palette;
 mod plot;
+mod profiler;
+mod props;
+mod recording;
+mod runtime_events;
+mod timeline;
+mod watch;
+
+use activity::Activity;
+use controls::Throughput;
+use graph::{PngExport, TopologyExport, TopologyView, show_marks};
+use inspector::{LogFilter, ModuleInspector, StateFormat, remove_empty, unify};
+use log_view::LogView;
+use palette::{Command, CommandPalette};
+use profiler::{ProfiledEvent, Profiler};
+use recording::{Input, Recorder};
+use timeline::Timeline;
+use tracing::{Event as LogEvent, GuiTracingObserver, LogFormat};
+use watch::WatchTable;
+
+/// The frame rate cap, unless changed in the controls.
+const DEFAULT_FPS: u32 = 30;
+
+/// The dispatch time per frame, above which the events per frame are
+/// reduced, unless changed in the parameters.
+const SLOW_FRAME: Duration = Duration::from_millis(100);
+/// How long the warning about reduced events per frame is shown.
+const THROTTLE_WARNING: Duration = Duration::from_secs(5);
+
+/// The seed used if the config does not specify one.
+pub const DEFAULT_SEED: u64 = 123;
+
+/// Launches the GUI. `f` builds the runtime from a seed, it is called again
+/// whenever the sim is reset, rewound or restarted with another seed.
+pub fn launch_with_gui(f: impl Fn(u64) -> Runtime<Sim<()>> + 'static) -> eframe::Result {
+    launch_with_config(f, GuiConfig::default())
+}
 
-use inspector::{ModuleInspector, remove_empty, unify};
-use tracing::GuiTracingObserver;
-
-pub fn launch_with_gui(f: impl FnOnce() -> Runtime<Sim<()>>) -> eframe::Result {
+/// Launches the GUI with initial breakpoints, traces, layout and window
+/// settings taken from `config`.
+///
+/// They are registered before the first frame and behave like those
+/// created in the GUI:
+///
+/// ```no_run
+/// use des_gui::{BreakpointKind, config::GuiConfig, launch_with_config};
+///
+/// let config = GuiConfig::default()
+///     .with_breakpoint("ping", "counter", BreakpointKind::OnValueChanged)
+///     .with_trace("pong", "counter");
+/// launch_with_config(des_gui::sim::sim_seeded, config).unwrap();
+/// ```
+pub fn launch_with_config(
+    f: impl Fn(u64) -> Runtime<Sim<()>> + 'static,
+    config: GuiConfig,
+) -> eframe::Result {
     let mut native_options = eframe::NativeOptions::default();
-    native_options.viewport.maximized = Some(true);
+    native_options.viewport.maximized = Some(config.window.maximized);
+    if let Some(size) = config.window.size {
+        native_options.viewport.inner_size = Some(size.into());
+    }
 
     let supress = var("DES_NOGUI").is_ok_and(|v| v == "1");
     if supress {
-        let _ = f().run().assert_no_err();
+        let _ = f(config.seed.unwrap_or(DEFAULT_SEED)).run().assert_no_err();
         return Ok(());
     }
 
+    let title = config.window.title.clone();
     eframe::run_native(
-        "des-gui",
+        &title,
         native_options,
-        Box::new(|cc| Ok(Box::new(Application::new(cc, f)))),
+        Box::new(|cc| Ok(Box::new(Application::new(cc, f, config)))),
     )
 }
 
 pub enum ActionReq {
     Breakpoint(BreakpointReq),
     Trace(TreeTraceReq),
+    /// Plots the second property against the first.
+    TraceVs((TreeTraceReq, TreeTraceReq)),
+    TraceSequence(TreeTraceReq),
+    /// Combines the property of the module and all its siblings.
+    TraceAcross((TreeTraceReq, Combine)),
+    Watch(TreeTraceReq),
+    SetProp(SetPropReq),
+    OpenInspector(ObjectPath),
+    /// Asks for a file to write the value of an inspected module to.
+    DumpState(DumpStateReq),
+    /// Writes the logs of a module to the output directory.
+    ExportLogs(ObjectPath),
 }
 
 pub type TreeTraceReq = (ObjectPath, String);
+/// The path, key and current value of a new breakpoint. Breakpoints are
+/// removed in the breakpoint panel, since a key may have several.
 pub type BreakpointReq = (ObjectPath, String, Option<Value>);
+pub type SetPropReq = (ObjectPath, String, Value);
+pub type DumpStateReq = (ObjectPath, Value, StateFormat);
+
+const TRACES_STORAGE_KEY: &str = "des-gui-traces";
+const SESSION_STORAGE_KEY: &str = "des-gui-session";
+/// The number of closed inspectors that can be reopened.
+const MAX_CLOSED: usize = 8;
 
-/// We derive Deserialize/Serialize so we can persist app state on shutdown.
+/// The debugger state. Inspectors, breakpoints, traces and the layout are
+/// persisted on shutdown, see `eframe::App::save`.
 pub struct Application {
     // Example stuff:
     logs: GuiTracingObserver,
-    last_frame: Instant,
 
     rt: Rt,
+    factory: Box<dyn Fn(u64) -> Runtime<Sim<()>>>,
+    seed: u64,
+    // The pending input of "Restart with seed"
+    seed_input: String,
+    baseline: Option<Baseline>,
     param: ExecutionParameters,
+    run: RunState,
+    // Prop edits with the number of events dispatched before each, which a
+    // replay re-applies
+    prop_edits: Vec<(usize, SetPropReq)>,
 
     dir: PathBuf,
 
     // Value observers
     observe: Observer,
     breakpoints: Vec<Breakpoint>,
+    // The breakpoint that stopped the sim, highlighted until continued.
+    hit: Option<usize>,
+    scroll_to_hit: bool,
+    // The log event that stopped the sim, shown until dismissed.
+    log_hit: Option<LogEvent>,
+
+    timeline: Timeline,
 
     // presenters
     modals: Vec<ModuleInspector>,
+    // The log filters of closed inspectors, restored when reopened.
+    log_filters: FxHashMap<ObjectPath, LogFilter>,
+    // Recently closed inspectors, the most recent last
+    closed: Vec<ModuleInspector>,
+    graph: Option<TopologyView>,
+    png: PngExport,
+    topo_export: TopologyExport,
     traces: Vec<Vec<Box<dyn Tracer>>>,
+    watches: Vec<WatchTable>,
+    isolated: FxHashMap<usize, String>,
+    // Traces not drawn, by name, independent of their group
+    hidden: FxHashSet<String>,
+    // Plot groups with a log y-axis, by index.
+    log_scale: FxHashSet<usize>,
+    // Plot groups marking the logs of traced modules, by index.
+    log_overlay: FxHashSet<usize>,
+    // Plot groups with a fixed y range, by index. Unlike the x axis, the y
+    // axis does not follow the data then, so that spikes do not flatten it.
+    y_ranges: FxHashMap<usize, (f64, f64)>,
+    // Drawn on all plot groups, thus independent of the group layout
+    markers: Vec<Marker>,
+    // The sim time hovered in any plot during the last frame
+    plot_cursor: Option<f64>,
+    csv_dialog: FileDialog,
+    plot_image: PlotScreenshot,
+    csv_group: usize,
+    logs_dialog: FileDialog,
+    logs_format: LogFormat,
+    // The module state waiting for `dump_dialog` to pick a file
+    dump: Option<(Value, StateFormat)>,
+    dump_dialog: FileDialog,
+    recorder: Recorder,
 
     // helpers
     tx_rx: (Sender<ActionReq>, Receiver<ActionReq>),
+    commands: Vec<Command>,
+    palette: Option<CommandPalette>,
 
     frame_time: Duration,
+    throughput: Throughput,
+    // When and to how many events per frame dispatching was throttled
+    throttled: Option<(Instant, usize)>,
+
+    // Set once a module handler panicked, the runtime must not be
+    // dispatched any further.
+    panic: Option<String>,
+    show_panic: bool,
 
     show_module_selection: bool,
+    module_filter: String,
+    // Modules checked in the sidebar, opened together on request
+    module_selected: FxHashSet<ObjectPath>,
     show_breakpoints: bool,
     show_graph: bool,
     show_errors: bool,
+    show_profiler: bool,
+    show_event_counts: bool,
+    show_activity: bool,
+    show_runtime_events: bool,
+    show_log_view: bool,
+    log_view: LogView,
+
+    profiler: Profiler,
+    // Dispatched events per module, attributed via the log watermark.
+    event_counts: FxHashMap<ObjectPath, usize>,
+    activity: Activity,
 }
 
 #[derive(Debug, Default)]
 struct Observer {
     map: FxHashMap<ObjectPath, Value>,
+    channels: ChannelObserver,
+    // Observed paths that can no longer be resolved.
+    stale: FxHashSet<ObjectPath>,
 }
 
 impl Observer {
     fn update(&mut self, sim: &Sim<()>) {
+        self.update_where(sim, |_| true);
+    }
+
+    /// Like [`Observer::update`], but only reloads the paths for which `f`
+    /// holds. Channel stats are still tracked, to keep utilization exact.
+    fn update_where(&mut self, sim: &Sim<()>, f: impl Fn(&ObjectPath) -> bool) {
+        self.channels.update(sim);
+
         for (path, value) in &mut self.map {
-            let Some(module) = sim.globals().get(&path) else {
+            if !f(path) {
+                continue;
+            }
+            let map = if is_channel_path(path) {
+                self.channels.load(sim, path)
+            } else {
+                sim.globals().get(&path).map(load_props_value)
+            };
+
+            let Some(map) = map else {
+                if self.stale.insert(path.clone()) {
+                    ::tracing::warn!("observed path no longer exists: {}", path);
+                }
                 continue;
             };
 
-            let map = load_props_value(module);
+            self.stale.remove(path);
             *value = Value::Mapping(map);
         }
     }
+
+    fn is_stale(&self, path: &ObjectPath) -> bool {
+        self.stale.contains(path)
+    }
+
+    fn load(&mut self, sim: &Sim<()>, path: &ObjectPath) -> Option<Value> {
+        let map = if is_channel_path(path) {
+            self.channels.load(sim, path)?
+        } else {
+            load_props_value(sim.globals().get(path)?)
+        };
+        Some(Value::Mapping(map))
+    }
 }
 
 impl Deref for Observer {
@@ -122,6 +318,8 @@ impl DerefMut for Observer {
 enum Rt {
     Runtime(Runtime<Sim<()>>),
     Finished(RuntimeResult<Sim<()>>),
+    // Only while `Rt::finish` has moved the runtime out
+    Finishing,
 }
 
 impl Rt {
@@ -129,41 +327,169 @@ impl Rt {
         match self {
             Self::Runtime(rt) => &rt.app,
             Self::Finished(res) => &res.app,
+            Self::Finishing => unreachable!("the runtime is restored within Rt::finish"),
         }
     }
 
-    fn finish(&mut self) -> Result<(), des::net::Error> {
+    fn time(&self) -> SimTime {
         match self {
-            Self::Runtime(rt) => {
-                unsafe {
-                    let runtime = std::ptr::read(rt);
-                    let replacing = runtime.finish();
+            Self::Runtime(rt) => rt.sim_time(),
+            Self::Finished(res) => res.time,
+            Self::Finishing => unreachable!("the runtime is restored within Rt::finish"),
+        }
+    }
 
-                    if let Some(err) = &replacing.error {
-                        println!("{err}");
-                    }
+    fn events(&self) -> usize {
+        match self {
+            Self::Runtime(rt) => rt.num_events_dispatched(),
+            Self::Finished(res) => res.profiler.event_count,
+            Self::Finishing => unreachable!("the runtime is restored within Rt::finish"),
+        }
+    }
 
-                    let replacing = Rt::Finished(replacing);
-                    let zeroed = mem::replace(self, replacing);
-                    forget(zeroed);
-                };
+    /// Finishes the runtime, e.g. running `at_sim_end` of all modules.
+    ///
+    /// If finishing panics, the runtime is lost, since finishing consumes
+    /// it. It is then replaced by a fresh one from `rebuild`, so that the
+    /// sim remains readable, and the caller must reset all views to it.
+    fn finish(&mut self, rebuild: impl FnOnce() -> Runtime<Sim<()>>) -> Result<(), String> {
+        self.finish_with(Runtime::finish, rebuild)
+    }
+
+    fn finish_with(
+        &mut self,
+        finish: impl FnOnce(Runtime<Sim<()>>) -> RuntimeResult<Sim<()>>,
+        rebuild: impl FnOnce() -> Runtime<Sim<()>>,
+    ) -> Result<(), String> {
+        if !matches!(self, Self::Runtime(_)) {
+            return Ok(());
+        }
+        let Self::Runtime(runtime) = mem::replace(self, Self::Finishing) else {
+            unreachable!("checked above")
+        };
+
+        match panic::catch_unwind(AssertUnwindSafe(|| finish(runtime))) {
+            Ok(result) => {
+                if let Some(err) = &result.error {
+                    println!("{err}");
+                }
+                *self = Self::Finished(result);
+                Ok(())
+            }
+            Err(payload) => {
+                *self = Self::Runtime(rebuild());
+                Err(panic_message(&*payload))
             }
-            _ => {}
         }
-        Ok(())
+    }
+}
+
+/// A point in the simulation that "Reset" returns to.
+///
+/// `Sim<()>` cannot be cloned, so a baseline is restored by rebuilding the
+/// runtime from the factory and replaying it up to `events`. This relies on
+/// the factory producing a deterministic (e.g. seeded) runtime.
+#[derive(Debug, Clone, Copy)]
+pub struct Baseline {
+    time: SimTime,
+    events: usize,
+}
+
+/// Whether the sim is dispatching events in the gui.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum RunState {
+    Running,
+    Paused,
+    /// Dispatches this many more events, then pauses.
+    Stepping(usize),
+    /// The sim ended, only a reset or restart runs it again.
+    Finished,
+}
+
+impl RunState {
+    /// The most events to dispatch, `None` if unbounded.
+    fn budget(self) -> Option<usize> {
+        match self {
+            Self::Running => None,
+            Self::Stepping(n) => Some(n),
+            Self::Paused | Self::Finished => Some(0),
+        }
+    }
+
+    /// The state once `steps` events were dispatched.
+    fn advance(self, steps: usize) -> Self {
+        match self {
+            Self::Stepping(n) if n <= steps => Self::Paused,
+            Self::Stepping(n) => Self::Stepping(n - steps),
+            other => other,
+        }
+    }
+
+    fn is_paused(self) -> bool {
+        self.budget() == Some(0)
+    }
+
+    /// Runs a paused sim and pauses a running one.
+    fn toggled(self) -> Self {
+        match self {
+            Self::Running => Self::Paused,
+            Self::Paused | Self::Stepping(_) => Self::Running,
+            Self::Finished => Self::Finished,
+        }
     }
 }
 
 #[derive(Default, Debug)]
 pub struct ExecutionParameters {
-    limit: Option<usize>,
+    /// The number of events to dispatch before stopping, `None` to run
+    /// until the sim ends. Only used by [`run_headless`], the gui is
+    /// controlled via its `RunState`.
+    pub limit: Option<usize>,
     per_frame_count: usize,
     per_event_time: Duration,
+    /// Stop once the sim reaches this time.
+    pub until: Option<SimTime>,
+    /// Frames per second while the sim is running, 0 for uncapped.
+    target_fps: u32,
+    /// Stop once any module logs at this level or above.
+    pub log_break_level: Option<::tracing::Level>,
+    /// Reload observed values only every n events. Paths with breakpoints
+    /// or watches are still reloaded after every event.
+    pub sample_every: usize,
+    /// Halve the events per frame once dispatching them takes longer than
+    /// this, e.g. due to expensive breakpoints.
+    pub slow_frame: Duration,
+}
+
+impl ExecutionParameters {
+    /// The frame budget, which bounds the adaptive events per frame.
+    fn frame_budget(&self) -> Duration {
+        match self.target_fps {
+            0 => Duration::from_millis(33),
+            fps => Duration::from_secs(1) / fps,
+        }
+    }
+
+    /// How long to wait until the next frame, if the current one started
+    /// at `frame_start`. Uncapped frame rates repaint immediately.
+    fn repaint_after(&self, frame_start: Instant, now: Instant) -> Duration {
+        match self.target_fps {
+            0 => Duration::ZERO,
+            fps => {
+                let next_frame = frame_start + Duration::from_secs(1) / fps;
+                next_frame.saturating_duration_since(now)
+            }
+        }
+    }
 }
 
 impl Application {
     /// Called once before the first frame.
-    pub fn new(cc: &eframe::CreationContext<'_>, f: impl FnOnce() -> Runtime<Sim<()>>) -> Self {
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        f: impl Fn(u64) -> Runtime<Sim<()>> + 'static,
+        mut config: GuiConfig,
+    ) -> Self {
         if env::var("RUST_LOG").is_err() {
             unsafe {
                 env::set_var("RUST_LOG", "winit=warn,trace");
@@ -201,132 +527,673 @@ impl Application {
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
 
-        let runtime = f();
-
-        Self {
-            last_frame: Instant::now(),
+        let seed = config.seed.unwrap_or(DEFAULT_SEED);
+        let runtime = f(seed);
+        let commands = Command::registry(&runtime.app);
 
+        let mut app = Self {
             param: ExecutionParameters {
-                limit: Some(0),
+                limit: None,
                 per_frame_count: 0,
                 per_event_time: Duration::ZERO,
+                until: None,
+                target_fps: DEFAULT_FPS,
+                log_break_level: None,
+                sample_every: 1,
+                slow_frame: SLOW_FRAME,
             },
             rt: Rt::Runtime(runtime),
+            factory: Box::new(f),
+            seed,
+            seed_input: seed.to_string(),
+            baseline: None,
+            run: RunState::Paused,
             logs: gui_capture,
 
             dir: temp_dir(),
 
             observe: Observer::default(),
             breakpoints: Vec::new(),
+            hit: None,
+            scroll_to_hit: false,
+            log_hit: None,
+
+            timeline: Timeline::default(),
 
-            // graph: generate_graph(topo),
             modals: Vec::new(),
+            log_filters: FxHashMap::default(),
+            closed: Vec::new(),
+            graph: None,
+            png: PngExport::default(),
+            topo_export: TopologyExport::default(),
             traces: vec![Vec::new()],
+            watches: Vec::new(),
+            isolated: FxHashMap::default(),
+            hidden: FxHashSet::default(),
+            log_scale: FxHashSet::default(),
+            log_overlay: FxHashSet::default(),
+            y_ranges: FxHashMap::default(),
+            markers: Vec::new(),
+            plot_cursor: None,
+            csv_dialog: FileDialog::new(),
+            plot_image: PlotScreenshot::default(),
+            csv_group: 0,
+            logs_dialog: FileDialog::new(),
+            logs_format: LogFormat::Json,
+            dump: None,
+            dump_dialog: FileDialog::new(),
+            recorder: Recorder::new(),
 
             tx_rx: channel(),
+            commands,
+            palette: None,
 
             frame_time: Duration::ZERO,
+            throughput: Throughput::default(),
+            throttled: None,
+
+            panic: None,
+            show_panic: false,
 
             show_module_selection: true,
+            module_filter: String::new(),
+            module_selected: FxHashSet::default(),
             show_breakpoints: false,
             show_graph: false,
             show_errors: false,
+            show_profiler: false,
+            show_event_counts: false,
+            show_activity: false,
+            show_runtime_events: false,
+            show_log_view: false,
+            log_view: LogView::default(),
+
+            profiler: Profiler::default(),
+            event_counts: FxHashMap::default(),
+            activity: Activity::default(),
+            prop_edits: Vec::new(),
+        };
+        // An explicit config replaces the previous session, rather than being
+        // overridden by or merged with it
+        let explicit = config != GuiConfig::default();
+        if let Some(storage) = cc.storage
+            && let Some(snapshots) =
+                eframe::get_value::<Vec<TraceSnapshot>>(storage, TRACES_STORAGE_KEY)
+        {
+            app.restore_traces(snapshots);
+        }
+        // Restored traces keep their points, the config must not add them twice
+        config
+            .traces
+            .retain(|t| !app.traces.iter().flatten().any(|r| r.config() == *t));
+        app.apply_config(config);
+        if !explicit
+            && let Some(storage) = cc.storage
+            && let Some(session) = eframe::get_value::<GuiConfig>(storage, SESSION_STORAGE_KEY)
+        {
+            app.restore_session(session);
         }
+        app
+    }
+
+    /// The current inspectors, breakpoints and layout. Traces are persisted
+    /// separately, including their points.
+    fn session(&self) -> GuiConfig {
+        GuiConfig {
+            inspectors: self.modals.iter().map(|m| m.path.to_string()).collect(),
+            breakpoints: self
+                .breakpoints
+                .iter()
+                .map(|b| BreakpointConfig {
+                    path: b.path.to_string(),
+                    key: b.key.clone(),
+                    kind: b.kind.clone(),
+                })
+                .collect(),
+            layout: LayoutConfig {
+                modules: self.show_module_selection,
+                breakpoints: self.show_breakpoints,
+                graph: self.show_graph,
+                errors: self.show_errors,
+            },
+            log_colors: self.logs.colors(),
+            ..GuiConfig::default()
+        }
+    }
+
+    /// Re-registers a previous session against the fresh sim. Simulation
+    /// progress is not restored.
+    fn restore_session(&mut self, session: GuiConfig) {
+        self.show_module_selection = session.layout.modules;
+        self.show_breakpoints = session.layout.breakpoints;
+        self.show_graph = session.layout.graph;
+        self.show_errors = session.layout.errors;
+        self.logs.set_colors(session.log_colors);
+
+        for path in session.inspectors {
+            self.open_inspector(ObjectPath::from(path));
+        }
+        for b in session.breakpoints {
+            self.add_breakpoint(b);
+        }
+    }
+
+    /// Adds a breakpoint, unless an identical one already exists.
+    fn add_breakpoint(&mut self, b: BreakpointConfig) {
+        let path = ObjectPath::from(b.path);
+        if breakpoint::exists(&self.breakpoints, &path, &b.key, &b.kind) {
+            return;
+        }
+        self.observe_path(&path);
+        let last = self.observe.get(&path).and_then(|v| access(v, &b.key));
+        self.breakpoints
+            .push(Breakpoint::new(path, b.key, b.kind, last));
+    }
+
+    fn apply_config(&mut self, config: GuiConfig) {
+        self.run = match config.mode {
+            ExecutionMode::Paused => RunState::Paused,
+            ExecutionMode::Running => RunState::Running,
+        };
+        if let Some(per_frame_count) = config.events_per_frame {
+            self.param.per_frame_count = per_frame_count;
+        }
+
+        self.show_module_selection = config.layout.modules;
+        self.show_breakpoints = config.layout.breakpoints;
+        self.show_graph = config.layout.graph;
+        self.show_errors = config.layout.errors;
+        self.logs.set_colors(config.log_colors);
+
+        for path in config.inspectors {
+            self.open_inspector(ObjectPath::from(path));
+        }
+        for b in config.breakpoints {
+            self.add_breakpoint(b);
+        }
+        for t in config.traces {
+            let tracer = self.tracer_from_config(t);
+            self.traces[0].push(tracer);
+        }
+    }
+
+    /// Starts observing a module or channel, if not already observed.
+    fn observe_path(&mut self, path: &ObjectPath) {
+        if self.observe.contains_key(path) {
+            return;
+        }
+        if let Some(value) = self.observe.load(self.rt.sim(), path) {
+            self.observe.insert(path.clone(), value);
+        } else {
+            ::tracing::warn!("cannot observe unknown path: {}", path);
+        }
+    }
+
+    /// Opens an inspector for a module or channel, if not already open.
+    fn open_inspector(&mut self, path: ObjectPath) {
+        if self.modals.iter().any(|m| m.path == path) {
+            return;
+        }
+
+        self.observe_path(&path);
+        if !self.observe.contains_key(&path) {
+            return;
+        }
+        let mut inspector = ModuleInspector::new(path, self.logs.clone());
+        inspector.nodes = self.rt.sim().nodes().into_iter().collect();
+        if let Some(filter) = self.log_filters.remove(&inspector.path) {
+            inspector.restore_log_filter(filter);
+        }
+        self.modals.push(inspector);
+    }
+
+    /// Writes the logs of a module to the output directory.
+    fn export_logs(&self, path: &ObjectPath) {
+        if let Err(e) = self.logs.export(path, &self.dir) {
+            ::tracing::error!("failed to export logs of {path}: {e}");
+        }
+    }
+
+    /// Reopens the most recently closed inspector as it was closed, e.g.
+    /// with its filter and pinned value.
+    fn reopen_closed(&mut self) {
+        let Some(mut inspector) = self.closed.pop() else {
+            return;
+        };
+        if self.modals.iter().any(|m| m.path == inspector.path) {
+            return;
+        }
+
+        self.observe_path(&inspector.path);
+        if !self.observe.contains_key(&inspector.path) {
+            return;
+        }
+        // Reopening restores the filter, a stale copy must not override it
+        self.log_filters.remove(&inspector.path);
+        inspector.remove = false;
+        inspector.nodes = self.rt.sim().nodes().into_iter().collect();
+        self.modals.push(inspector);
+    }
+
+    /// Rebuilds the runtime and fast-forwards it to `events` dispatched events.
+    ///
+    /// Logs are regenerated by the replay, traces are truncated to the
+    /// restored time and breakpoints resume from the restored values. Prop
+    /// edits are re-applied after as many events as when they were made,
+    /// later edits are dropped. A recording or replay in progress is stopped.
+    fn replay_to(&mut self, events: usize) {
+        self.recorder.stop();
+        self.param.until = None;
+        self.logs.clear();
+        self.profiler.clear();
+        self.event_counts.clear();
+        self.activity.clear();
+        self.panic = None;
+        self.observe.channels = ChannelObserver::default();
+        self.rt = Rt::Runtime((self.factory)(self.seed));
+
+        let Rt::Runtime(ref mut runtime) = self.rt else {
+            unreachable!("runtime was just built")
+        };
+        self.prop_edits.retain(|(at, _)| *at <= events);
+        let mut dispatched = 0;
+        for (at, (path, key, value)) in &self.prop_edits {
+            fast_forward(runtime, *at - dispatched);
+            dispatched = *at;
+            if let Err(e) = props::set_prop(&runtime.app, path, key, value.clone()) {
+                ::tracing::warn!("failed to re-apply {path} {key}: {e}");
+            }
+        }
+        fast_forward(runtime, events - dispatched);
+        self.observe.update(&runtime.app);
+        // The replay may log above the break level, but must not stop
+        self.logs.take_tripped();
+        self.log_hit = None;
+
+        let now = SimTime::now();
+        self.traces
+            .iter_mut()
+            .flatten()
+            .for_each(|trace| trace.truncate(now));
+        for watch in &mut self.watches {
+            watch.truncate(now);
+        }
+        self.markers.retain(|m| m.time <= now);
+        for b in &mut self.breakpoints {
+            b.reset(&self.observe);
+        }
+
+        self.run = RunState::Paused;
+    }
+
+    /// Changes the run state, unless the sim finished, which only a reset
+    /// or restart undoes. Pausing drops the target of a timeline jump.
+    fn set_run(&mut self, run: RunState) {
+        if self.run != RunState::Finished {
+            if run == RunState::Paused {
+                self.param.until = None;
+            }
+            self.run = run;
+            self.recorder.record(self.rt.events(), Input::Run(run));
+        }
+    }
+
+    /// Restarts the simulation from the baseline, or from the start if no
+    /// custom baseline is set.
+    fn reset(&mut self) {
+        self.replay_to(self.baseline.map_or(0, |b| b.events));
+    }
+
+    /// Rebuilds the runtime with another seed. The baseline, timeline and
+    /// prop edits belong to the previous run and are dropped.
+    fn restart_with_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.baseline = None;
+        self.timeline = Timeline::default();
+        self.prop_edits.clear();
+        self.replay_to(0);
     }
 
     fn run_sim_step(&mut self, ctx: &egui::Context) -> ControlFlow<()> {
+        self.replay_due_inputs();
+
         // setup tracers
         while let Ok(req) = self.tx_rx.1.try_recv() {
+            self.recorder.record_action(self.rt.events(), &req);
             match req {
                 ActionReq::Breakpoint(req) => {
                     self.show_breakpoints = true;
-                    if let Some(i) = self
-                        .breakpoints
-                        .iter()
-                        .position(|b| b.path == req.0 && b.key == req.1)
-                    {
-                        self.breakpoints.remove(i);
-                    } else {
-                        self.breakpoints.push(Breakpoint {
-                            path: req.0,
-                            key: req.1,
-                            kind: BreakpointKind::OnValueChanged,
-                            last: req.2,
-                            triggered: false,
-                            remove: false,
-                        });
-                    }
+                    breakpoint::request(&mut self.breakpoints, req);
                 }
                 ActionReq::Trace(req) => {
                     self.traces[0].push(Box::new(TreeTracer::new(req.0, req.1)));
                 }
+                ActionReq::Watch((path, key)) => {
+                    if !self.watches.iter().any(|w| w.path == path && w.key == key) {
+                        let mut watch = WatchTable::new(path, key);
+                        watch.update(&self.observe);
+                        self.watches.push(watch);
+                    }
+                }
+                ActionReq::TraceSequence(req) => {
+                    self.traces[0].push(Box::new(SequenceTracer::new(req.0, req.1)));
+                }
+                ActionReq::TraceAcross(((path, key), combine)) => {
+                    let tracer = self.tracer_from_config(TraceConfig {
+                        path: plot::siblings_pattern(&path),
+                        key,
+                        vs: None,
+                        sequence: false,
+                        max_points: None,
+                        combine: Some(combine),
+                        derive: None,
+                        smooth: None,
+                    });
+                    self.traces[0].push(tracer);
+                }
+                ActionReq::TraceVs((x, y)) => {
+                    self.observe_path(&x.0);
+                    self.observe_path(&y.0);
+                    self.traces[0].push(Box::new(XYTracer::new(x, y)));
+                }
+                ActionReq::OpenInspector(path) => self.open_inspector(path),
+                ActionReq::ExportLogs(path) => self.export_logs(&path),
+                ActionReq::DumpState((path, value, format)) => {
+                    self.dump_dialog = FileDialog::new()
+                        .initial_directory(self.dir.clone())
+                        .default_file_name(&format!("{path}.state.{}", format.extension()));
+                    self.dump_dialog.save_file();
+                    self.dump = Some((value, format));
+                }
+                ActionReq::SetProp((path, key, value)) => {
+                    let result = match &self.rt {
+                        Rt::Runtime(runtime) => {
+                            props::set_prop(&runtime.app, &path, &key, value.clone())
+                        }
+                        Rt::Finished(_) | Rt::Finishing => {
+                            Err("the simulation has finished".to_string())
+                        }
+                    };
+
+                    match result {
+                        Ok(()) => {
+                            // Reflect the new value without waiting for the next event
+                            if let Some(value) = self.observe.load(self.rt.sim(), &path) {
+                                self.observe.insert(path.clone(), value);
+                            }
+                            self.prop_edits.push((self.rt.events(), (path, key, value)));
+                        }
+                        Err(e) => {
+                            ::tracing::warn!("failed to set {path} {key}: {e}");
+                            if let Some(modal) = self.modals.iter_mut().find(|m| m.path == path) {
+                                modal.rejected = Some(Instant::now());
+                            }
+                        }
+                    }
+                }
             }
         }
 
+        if self.panic.is_some() {
+            return ControlFlow::Continue(());
+        }
+        self.logs.set_break_level(self.param.log_break_level);
+
         if let Rt::Runtime(ref mut runtime) = self.rt {
             if runtime.was_started()
                 && (runtime.has_reached_limit() || runtime.num_events_remaining() == 0)
             {
-                self.rt.finish().expect("failed");
+                self.run = match self.rt.finish(|| (self.factory)(self.seed)) {
+                    Ok(()) => RunState::Finished,
+                    Err(message) => {
+                        // Logs, traces and breakpoints must not describe the lost run
+                        self.replay_to(0);
+                        self.panic = Some(message);
+                        self.show_panic = true;
+                        RunState::Paused
+                    }
+                };
                 ctx.request_repaint();
                 // TODO update observers
                 return ControlFlow::Break(());
             }
 
-            let can_progress = (self.param.limit.map_or(true, |v| v > 0)
-                && runtime.num_events_remaining() > 0)
+            let can_progress = (!self.run.is_paused() && runtime.num_events_remaining() > 0)
                 || !runtime.was_started();
             if can_progress {
                 if self.param.per_frame_count >= 1_000
                     && !self.frame_time.is_zero()
                     && !self.param.per_event_time.is_zero()
                 {
-                    // STEPS MAX
-                    const FRAME_MAX: Duration = Duration::from_millis(33);
-                    let remaining = FRAME_MAX.saturating_sub(self.frame_time).as_secs_f64();
+                    let remaining = self
+                        .param
+                        .frame_budget()
+                        .saturating_sub(self.frame_time)
+                        .as_secs_f64();
                     let count = remaining / self.param.per_event_time.as_secs_f64() / 1.5;
                     self.param.per_frame_count = (count as usize).max(1_000);
                 }
 
-                let steps = self.param.per_frame_count;
+                let steps = match self.run.budget() {
+                    Some(budget) => budget.min(self.param.per_frame_count),
+                    None => self.param.per_frame_count,
+                };
 
                 if !runtime.was_started() {
                     runtime.start().expect("failed to start");
                 }
 
+                // Guards are checked after every event, other traces once per frame
+                let mut guarded = self
+                    .traces
+                    .iter_mut()
+                    .flatten()
+                    .filter_map(|trace| trace.guard().is_some_and(|g| g.is_some()).then_some(trace))
+                    .collect::<Vec<_>>();
+
                 let t0 = Instant::now();
+                let start = runtime.num_events_dispatched();
+                let mut sampled = true;
                 'outer: for _ in 0..steps {
-                    runtime
-                        .dispatch_n_events(1)
-                        .expect("failed to dispatch events");
-
-                    self.observe.update(&runtime.app);
-
-                    for b in &mut self.breakpoints {
-                        if let ControlFlow::Break(()) = b.update(&self.observe) {
-                            self.param.limit = Some(0);
+                    self.logs.take_watermark();
+                    let t_event = self.profiler.enabled.then(Instant::now);
+
+                    sampled =
+                        (runtime.num_events_dispatched() + 1) % self.param.sample_every.max(1) == 0;
+                    let flow = step_event(
+                        runtime,
+                        &mut self.observe,
+                        &mut self.breakpoints,
+                        |path| {
+                            self.watches.iter().any(|w| w.path == *path)
+                                || guarded.iter().any(|t| t.needs_path(path))
+                        },
+                        sampled,
+                        |runtime| {
+                            let module = self.logs.take_watermark();
+                            if let Some(module) = &module {
+                                *self.event_counts.entry(module.clone()).or_default() += 1;
+                                self.activity.record(SimTime::now(), module.clone());
+                            }
+                            if let Some(t_event) = t_event {
+                                self.profiler.record(ProfiledEvent {
+                                    index: runtime.num_events_dispatched(),
+                                    time: SimTime::now(),
+                                    module,
+                                    duration: t_event.elapsed(),
+                                });
+                            }
+                        },
+                    );
+
+                    match flow {
+                        ControlFlow::Break(Stop::Panic(message)) => {
+                            self.panic = Some(message);
+                            self.show_panic = true;
+                            self.run = RunState::Paused;
+                            break 'outer;
+                        }
+                        ControlFlow::Break(Stop::Breakpoint(i)) => {
+                            self.run = RunState::Paused;
+                            self.hit = Some(i);
+                            self.scroll_to_hit = true;
+                            self.markers.push(Marker {
+                                time: SimTime::now(),
+                                label: self.breakpoints[i].key.clone(),
+                            });
+                            self.show_breakpoints = true;
                             break 'outer;
                         }
+                        ControlFlow::Continue(()) => {}
                     }
-                }
 
-                if steps > 0 {
-                    self.param.per_event_time = t0.elapsed() / steps as u32;
+                    let mut tripped = false;
+                    for trace in &mut guarded {
+                        tripped |= trace.update(&self.observe).is_break();
+                    }
+                    if tripped {
+                        self.run = RunState::Paused;
+                        break 'outer;
+                    }
+
+                    if let Some(event) = self.logs.take_tripped() {
+                        self.run = RunState::Paused;
+                        self.log_hit = Some(event);
+                        break 'outer;
+                    }
+                    // Replayed inputs apply at the next frame
+                    if self.recorder.is_due(runtime.num_events_dispatched()) {
+                        break 'outer;
+                    }
+
+                    for watch in &mut self.watches {
+                        watch.update(&self.observe);
+                    }
+
+                    if self
+                        .param
+                        .until
+                        .is_some_and(|until| SimTime::now() >= until)
+                    {
+                        self.param.until = None;
+                        self.run = RunState::Paused;
+                        break 'outer;
+                    }
                 }
-                // Update not per event but per frame: TODO is that a good idea?
-                self.traces
-                    .iter_mut()
-                    .for_each(|t| t.iter_mut().for_each(|trace| trace.update(&self.observe)));
 
-                if let Some(ref mut limit) = self.param.limit {
-                    *limit = limit.saturating_sub(steps);
+                // Inspectors and traces show the latest values, once per frame
+                if !sampled {
+                    self.observe.update(&runtime.app);
+                }
+                self.timeline
+                    .record(runtime.sim_time(), runtime.num_events_dispatched());
+
+                // The loop may stop early, e.g. at a replayed input
+                let dispatched = runtime.num_events_dispatched() - start;
+                let elapsed = t0.elapsed();
+                if dispatched > 0 {
+                    self.param.per_event_time = elapsed / dispatched as u32;
                 }
+                if elapsed > self.param.slow_frame && steps > 1 {
+                    self.param.per_frame_count = steps / 2;
+                    self.throttled = Some((Instant::now(), steps / 2));
+                }
+                for trace in self.traces.iter_mut().flatten() {
+                    if trace.guard().is_none_or(|g| g.is_none()) {
+                        let _ = trace.update(&self.observe);
+                    }
+                }
+                self.run = self.run.advance(dispatched);
             }
         };
         ControlFlow::Continue(())
     }
 }
 
+/// Why [`step_event`] stopped.
+enum Stop {
+    Panic(String),
+    /// The index of the triggered breakpoint.
+    Breakpoint(usize),
+}
+
+/// Dispatches one event, then updates the observers and checks the
+/// breakpoints, as shared by the GUI and [`run_headless`].
+///
+/// `after_dispatch` runs right after the handler returned, before any
+/// observation, e.g. to profile it.
+///
+/// Unless `sample` is set, only the paths with breakpoints or for which
+/// `reload` holds are reloaded.
+fn step_event(
+    runtime: &mut Runtime<Sim<()>>,
+    observe: &mut Observer,
+    breakpoints: &mut [Breakpoint],
+    reload: impl Fn(&ObjectPath) -> bool,
+    sample: bool,
+    after_dispatch: impl FnOnce(&Runtime<Sim<()>>),
+) -> ControlFlow<Stop> {
+    if let Err(message) = dispatch_event(runtime) {
+        return ControlFlow::Break(Stop::Panic(message));
+    }
+    after_dispatch(runtime);
+
+    match sample {
+        true => observe.update(&runtime.app),
+        // Breakpoints, watches and guards must see every value
+        false => observe.update_where(&runtime.app, |path| {
+            breakpoints.iter().any(|b| b.path == *path) || reload(path)
+        }),
+    }
+    for (i, b) in breakpoints.iter_mut().enumerate() {
+        if let ControlFlow::Break(()) = b.update(observe) {
+            return ControlFlow::Break(Stop::Breakpoint(i));
+        }
+    }
+    ControlFlow::Continue(())
+}
+
+/// Starts the runtime if needed and dispatches `n` events.
+fn fast_forward(runtime: &mut Runtime<Sim<()>>, n: usize) {
+    if n == 0 {
+        return;
+    }
+    if !runtime.was_started() {
+        runtime.start().expect("failed to start");
+    }
+    runtime
+        .dispatch_n_events(n)
+        .expect("failed to dispatch events");
+}
+
+/// Dispatches a single event, catching panics raised by module handlers.
+///
+/// The runtime is asserted to be `UnwindSafe`, which it is not: a handler
+/// that panics leaves its module, and possibly the runtime, mid-event. Thus
+/// no further events may be dispatched after an `Err`, but the sim can still
+/// be read, and captured logs and traces remain valid for inspection.
+fn dispatch_event(runtime: &mut Runtime<Sim<()>>) -> Result<(), String> {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        runtime
+            .dispatch_n_events(1)
+            .expect("failed to dispatch events");
+    }))
+    .map_err(|payload| panic_message(&*payload))
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic payload".to_string())
+}
+
+/// Case-insensitive substring match, `filter` must already be lowercase.
+fn matches_filter(path: &ObjectPath, filter: &str) -> bool {
+    filter.is_empty() || path.as_str().to_lowercase().contains(filter)
+}
+
 fn load_props_value(module: ModuleRef) -> Mapping {
     let props = module.props_keys();
     let props_with_values = props
@@ -347,6 +1214,12 @@ fn load_props_value(module: ModuleRef) -> Mapping {
 }
 
 impl eframe::App for Application {
+    /// Persists all traces, including their recorded points, and the session.
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, TRACES_STORAGE_KEY, &self.trace_snapshots());
+        eframe::set_value(storage, SESSION_STORAGE_KEY, &self.session());
+    }
+
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         let t0 = Instant::now();
@@ -359,10 +1232,66 @@ impl eframe::App for Application {
         }
 
         self.render_controls(ctx);
+        self.render_timeline(ctx);
+        if self.show_activity {
+            self.render_activity(ctx);
+        }
+        self.render_palette(ctx);
+
+        if self.show_panic
+            && let Some(message) = &self.panic
+        {
+            Modal::new(Id::new("panic-modal")).show(ctx, |ui| {
+                ui.label(
+                    RichText::new("A module panicked, the simulation was stopped")
+                        .strong()
+                        .color(Color32::RED),
+                );
+                ui.separator();
+                ui.label(message);
+                ui.separator();
+                ui.label("Logs and traces are kept for inspection. Reset to continue.");
+                if ui.button("Close").clicked() {
+                    self.show_panic = false;
+                }
+            });
+        }
 
-        self.modals.retain(|v| !v.remove);
+        for modal in self.modals.iter().filter(|m| m.remove) {
+            self.log_filters
+                .insert(modal.path.clone(), modal.log_filter());
+        }
+        let (closed, open) = std::mem::take(&mut self.modals)
+            .into_iter()
+            .partition::<Vec<_>, _>(|v| v.remove);
+        self.modals = open;
+        self.closed.extend(closed);
+        let excess = self.closed.len().saturating_sub(MAX_CLOSED);
+        self.closed.drain(..excess);
+        if self.breakpoints.iter().any(|b| b.remove) {
+            // Indices shift, so the hit can no longer be located
+            self.hit = None;
+        }
         self.breakpoints.retain(|v| !v.remove);
 
+        // Inspected paths are observed even if they were not when opened,
+        // e.g. a module that appeared only later
+        let inspected = self
+            .modals
+            .iter()
+            .flat_map(|m| [Some(m.path.clone()), m.compare.clone()])
+            .flatten()
+            .collect::<Vec<_>>();
+        for path in inspected {
+            // Unlike `observe_path`, missing modules are shown in the
+            // inspector rather than warned about every frame
+            if !self.observe.contains_key(&path)
+                && let Some(value) = self.observe.load(self.rt.sim(), &path)
+            {
+                self.observe.insert(path, value);
+            }
+        }
+
         for modal in &mut self.modals {
             ctx.show_viewport_immediate(
                 egui::ViewportId(Id::new(format!("panel-{}", modal.path))),
@@ -371,15 +1300,30 @@ impl eframe::App for Application {
                     .with_inner_size([800.0, 1200.0]),
                 |ctx, _| {
                     let tx = self.tx_rx.0.clone();
-                    CentralPanel::default().show(ctx, |ui| {
-                        modal.show(
-                            ui,
-                            self.observe
-                                .get(&modal.path)
-                                .expect("must be observerd")
-                                .clone(),
-                            tx,
-                        )
+                    let gates = match is_channel_path(&modal.path) {
+                        true => Vec::new(),
+                        false => self.observe.channels.gates(self.rt.sim(), &modal.path),
+                    };
+                    let other = modal.compare.as_ref().and_then(|p| self.observe.get(p));
+                    let breakpoints = self
+                        .breakpoints
+                        .iter()
+                        .filter(|b| b.path == modal.path)
+                        .map(|b| b.key.clone())
+                        .collect::<FxHashSet<_>>();
+                    CentralPanel::default().show(ctx, |ui| match self.observe.get(&modal.path) {
+                        Some(value) => {
+                            modal.show(ui, value.clone(), other, &gates, &breakpoints, tx)
+                        }
+                        None => {
+                            ui.weak(format!(
+                                "No data for {}, the module may not exist (anymore)",
+                                modal.path
+                            ));
+                            if ui.button("Close").clicked() {
+                                modal.remove = true;
+                            }
+                        }
                     });
                     if ctx.input(|i| i.viewport().close_requested()) {
                         // Tell parent to close us.
@@ -394,41 +1338,117 @@ impl eframe::App for Application {
         }
 
         if self.show_module_selection {
+            let mut open = None;
+            let mut open_selected = false;
+            let marks = self.marks();
             SidePanel::left("module-selection").show(ctx, |ui| {
-                let sim = match &self.rt {
-                    Rt::Runtime(r) => &r.app,
-                    Rt::Finished(r) => &r.app,
-                };
+                let sim = self.rt.sim();
 
                 ui.label(RichText::new("Breakpoints").strong());
+                ui.add(
+                    TextEdit::singleline(&mut self.module_filter)
+                        .hint_text("Filter modules")
+                        .desired_width(f32::INFINITY),
+                );
+                ui.add_enabled_ui(!self.module_selected.is_empty(), |ui| {
+                    let label = format!("Open selected ({})", self.module_selected.len());
+                    open_selected = ui.button(label).clicked();
+                });
                 ui.separator();
 
+                let filter = self.module_filter.to_lowercase();
+                let mut nodes = sim
+                    .nodes()
+                    .into_iter()
+                    .filter(|path| matches_filter(path, &filter))
+                    .collect::<Vec<_>>();
+                nodes.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
                 ScrollArea::vertical().show(ui, |ui| {
-                    for node_path in sim.nodes() {
+                    for node_path in nodes {
+                        ui.scope(|ui| {
+                            let exists = self.modals.iter().any(|n| n.path == node_path);
+
+                            if exists {
+                                self.module_selected.remove(&node_path);
+                                ui.disable();
+                            }
+                            ui.horizontal(|ui| {
+                                let mut selected = self.module_selected.contains(&node_path);
+                                if ui.checkbox(&mut selected, "").changed() {
+                                    match selected {
+                                        true => self.module_selected.insert(node_path.clone()),
+                                        false => self.module_selected.remove(&node_path),
+                                    };
+                                }
+                                if ui.button(node_path.as_str()).clicked() {
+                                    open = Some(node_path.clone());
+                                }
+                                if let Some(marks) = marks.get(&node_path) {
+                                    show_marks(ui, *marks);
+                                }
+                            });
+                        });
+                    }
+
+                    ui.separator();
+                    ui.label(RichText::new("Channels").strong());
+
+                    for channel_path in self
+                        .observe
+                        .channels
+                        .paths(sim)
+                        .into_iter()
+                        .filter(|path| matches_filter(path, &filter))
+                    {
                         ui.scope(|ui| {
-                            let node = sim.globals().get(&node_path).expect("node must exist");
-                            let exists = self.modals.iter().any(|n| n.path == node.path());
+                            let exists = self.modals.iter().any(|n| n.path == channel_path);
 
                             if exists {
                                 ui.disable();
                             }
-                            if ui.button(node_path.as_str()).clicked() {
-                                let value = load_props_value(node);
-                                self.observe
-                                    .insert(node_path.clone(), Value::Mapping(value));
-                                self.modals
-                                    .push(ModuleInspector::new(node_path, self.logs.clone()));
+                            if ui.button(channel_path.as_str()).clicked() {
+                                open = Some(channel_path);
                             }
                         });
                     }
                 });
             });
+
+            if let Some(path) = open {
+                self.open_inspector(path);
+            }
+            if open_selected {
+                let mut paths = self.module_selected.drain().collect::<Vec<_>>();
+                paths.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+                for path in paths {
+                    self.open_inspector(path);
+                }
+            }
         }
 
         if self.show_breakpoints {
             self.render_breakpoints(ctx);
         }
 
+        self.render_watches(ctx);
+
+        if self.show_profiler {
+            self.render_profiler(ctx);
+        }
+
+        if self.show_event_counts {
+            self.render_event_counts(ctx);
+        }
+
+        if self.show_runtime_events {
+            self.render_runtime_events(ctx);
+        }
+
+        if self.show_log_view {
+            self.render_log_view(ctx);
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             if self.show_errors
                 && let Rt::Finished(r) = &self.rt
@@ -445,87 +1465,116 @@ impl eframe::App for Application {
                 }
             }
 
-            if self.show_graph {
-                let path = format!("{}topo.png", self.dir.as_path().display());
-                if !fs::exists(&path).unwrap() {
-                    generate_graph(self.rt.sim(), &self.dir);
-                }
+            ui.label(format!("{:?}", self.frame_time));
 
-                ui.add(Image::new(format!("file://{path}")).shrink_to_fit());
+            if self.show_graph {
+                self.render_graph(ui);
             }
-
-            ui.label(format!("{:?}", self.frame_time))
         });
 
         // Remove observers if no longer needed
         for k in self.observe.keys().cloned().collect::<Vec<_>>() {
-            let needed = self.modals.iter().any(|m| m.path == k)
+            let needed = self
+                .modals
+                .iter()
+                .any(|m| m.path == k || m.compare.as_ref() == Some(&k))
                 || self.traces.iter().flatten().any(|v| v.needs_path(&k))
+                || self.watches.iter().any(|w| w.path == k)
                 || self.breakpoints.iter().any(|b| b.path == k);
             if !needed {
                 self.observe.remove(&k);
+                self.observe.stale.remove(&k);
                 ::tracing::info!("Removed observer for path: {}", k);
             }
         }
 
         if matches!(self.rt, Rt::Runtime(_)) {
-            let frame_time = Duration::from_secs(1) / 30;
-            let next_frame = self.last_frame + frame_time;
-            let now = Instant::now();
-            let wait_time = next_frame.max(now).duration_since(now);
-
-            ctx.request_repaint_after(wait_time);
+            // Paced from the start of this frame, so that the time spent
+            // dispatching counts towards the frame.
+            ctx.request_repaint_after(self.param.repaint_after(t0, Instant::now()));
         }
 
         self.frame_time = t0.elapsed();
     }
 }
 
-fn generate_graph(sim: &Sim<()>, dir: &Path) {
-    let topo = sim.topology();
-
-    let mut child = Command::new("dot")
-        .arg("-Tpng")
-        .arg("-Gdpi=300")
-        .arg("-Gfontcolor=white")
-        .arg("-Gcolor=white")
-        .arg("-Nfontcolor=white")
-        .arg("-Ncolor=white")
-        .arg("-Efontcolor=white")
-        .arg("-Ecolor=white")
-        .arg("-Gbgcolor=black")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .expect("dot failed");
-
-    let graph = topo.map(
-        |_, node| node.path().to_string(),
-        |_, edge| format!("{}*{}", edge.source.name(), edge.target.name()),
-    );
-    let dot = Dot::with_attr_getters(
-        &graph,
-        &[Config::NodeNoLabel, Config::EdgeNoLabel],
-        &|_, edge| {
-            let (l, r) = edge.weight().split_once("*").unwrap();
-            format!("headlabel={r:?} taillabel={l:?}")
-        },
-        &|_, node| format!("label={:?} shape=box", node.1),
-    );
-
-    println!("{dot}");
-
-    let mut stdin = child.stdin.take().expect("failed to open stdin");
-    stdin
-        .write_all(format!("{dot}").as_bytes())
-        .expect("write failed");
-    drop(stdin);
-
-    let output = child.wait_with_output().expect("wait failed");
-    File::create(format!("{}topo.png", dir.display()))
-        .unwrap()
-        .write_all(&output.stdout)
-        .unwrap();
-
-    ::tracing::info!("wrote topo to {}", format!("{}topo.png", dir.display()));
+#[cfg(test)]
+mod tests {
+    use des::net::handlers::HandlerFn;
+
+    use super::*;
+
+    #[test]
+    fn dispatch_event_catches_handler_panic() {
+        let mut sim = Sim::new(());
+        sim.node("panicking", HandlerFn::new(|_| panic!("deliberate panic")));
+        let gate = sim.gate("panicking", "in");
+
+        let mut rt = Builder::seeded(123).build(sim.freeze());
+        rt.add_message_onto(gate, Message::default(), SimTime::ZERO);
+        rt.start().expect("failed to start");
+
+        let message = dispatch_event(&mut rt).expect_err("handler must panic");
+        assert_eq!(message, "deliberate panic");
+
+        // The sim must still be readable for inspection
+        let module = rt
+            .app
+            .globals()
+            .get(&ObjectPath::from("panicking"))
+            .expect("module must exist");
+        assert!(load_props_value(module).is_empty());
+    }
+
+    #[test]
+    fn finish_recovers_from_panic() {
+        let mut rt = Rt::Runtime(crate::sim::sim());
+        let result = rt.finish_with(|_| panic!("deliberate panic"), crate::sim::sim);
+        assert_eq!(result, Err("deliberate panic".to_string()));
+        assert!(matches!(rt, Rt::Runtime(_)));
+        assert!(!rt.sim().nodes().is_empty());
+    }
+
+    #[test]
+    fn steps_then_pauses() {
+        let run = RunState::Stepping(5);
+        assert_eq!(run.budget(), Some(5));
+        assert_eq!(run.advance(3), RunState::Stepping(2));
+        assert_eq!(run.advance(5), RunState::Paused);
+        assert_eq!(RunState::Running.advance(100), RunState::Running);
+        assert!(RunState::Finished.is_paused());
+        assert_eq!(RunState::Stepping(1).toggled(), RunState::Running);
+        assert_eq!(RunState::Finished.toggled(), RunState::Finished);
+    }
+
+    #[test]
+    fn paces_repaints_from_frame_start() {
+        let mut param = ExecutionParameters {
+            target_fps: 10,
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let frame = Duration::from_millis(100);
+        assert_eq!(param.repaint_after(start, start), frame);
+        assert_eq!(
+            param.repaint_after(start, start + Duration::from_millis(30)),
+            Duration::from_millis(70)
+        );
+        // A frame over budget repaints immediately
+        assert_eq!(
+            param.repaint_after(start, start + 2 * frame),
+            Duration::ZERO
+        );
+
+        param.target_fps = 0;
+        assert_eq!(param.repaint_after(start, start), Duration::ZERO);
+    }
+
+    #[test]
+    fn module_filter_ignores_case() {
+        let path = ObjectPath::from("Router.eth0");
+        assert!(matches_filter(&path, "router"));
+        assert!(matches_filter(&path, ""));
+        assert!(!matches_filter(&path, "client"));
+    }
 }
@@ -0,0 +1,171 @@
+use std::cmp::Ordering;
+
+use des::net::ObjectPath;
+use egui::{Context, Label, RichText, Sense, TextEdit, TextStyle, Window};
+use egui_extras::{Column, TableBuilder};
+
+use crate::{Application, inspector::LEVELS, tracing::Event};
+
+/// The filter of the global log view, which lists the logs of all modules.
+#[derive(Debug, Clone)]
+pub struct LogView {
+    filter: String,
+    levels: [bool; LEVELS.len()],
+    /// The column the logs are sorted by, and whether descending.
+    sort: Option<(&'static str, bool)>,
+}
+
+impl Default for LogView {
+    fn default() -> Self {
+        Self {
+            filter: String::new(),
+            levels: [true; LEVELS.len()],
+            sort: None,
+        }
+    }
+}
+
+/// The columns of the table, all but the fields can be sorted by.
+const COLUMNS: [&str; 5] = ["Time", "Module", "Level", "Target", "Fields"];
+
+/// Compares two logs by a column, levels by severity.
+fn compare(column: &str, l: &Event, r: &Event) -> Ordering {
+    match column {
+        "Time" => l.time.partial_cmp(&r.time).unwrap_or(Ordering::Equal),
+        "Module" => l.module.as_str().cmp(r.module.as_str()),
+        "Level" => r.metadata.level().cmp(l.metadata.level()),
+        "Target" => l.metadata.target().cmp(r.metadata.target()),
+        _ => Ordering::Equal,
+    }
+}
+
+impl Application {
+    /// Lists the recent logs of all modules in one table, read from the
+    /// merged view of the observer, which is kept in time order. Clicking a
+    /// header sorts by that column instead.
+    pub fn render_log_view(&mut self, ctx: &Context) {
+        let colors = self.logs.colors();
+        let mut open_module = None::<ObjectPath>;
+        let mut open = self.show_log_view;
+        Window::new("Logs")
+            .open(&mut open)
+            .default_size([720.0, 360.0])
+            .show(ctx, |ui| {
+                let view = &mut self.log_view;
+                ui.horizontal(|ui| {
+                    ui.add(
+                        TextEdit::singleline(&mut view.filter)
+                            .hint_text("Search...")
+                            .desired_width(200.0),
+                    );
+                    for (level, shown) in LEVELS.iter().zip(&mut view.levels) {
+                        ui.toggle_value(
+                            shown,
+                            RichText::new(level.as_str()).color(colors.get(*level)),
+                        );
+                    }
+                });
+                ui.separator();
+
+                let merged = self.logs.merged.lock().expect("failed to lock");
+                let mut events = merged
+                    .iter()
+                    .filter(|event| {
+                        LEVELS
+                            .iter()
+                            .position(|l| l == event.metadata.level())
+                            .is_some_and(|i| view.levels[i])
+                    })
+                    .filter(|event| event.matches(&view.filter))
+                    .collect::<Vec<_>>();
+                if let Some((column, descending)) = view.sort {
+                    // Stable, so equal logs stay in time order
+                    events.sort_by(|l, r| {
+                        let ord = compare(column, l, r);
+                        match descending {
+                            true => ord.reverse(),
+                            false => ord,
+                        }
+                    });
+                }
+
+                let row_height = ui.text_style_height(&TextStyle::Body);
+                TableBuilder::new(ui)
+                    .striped(true)
+                    .stick_to_bottom(view.sort.is_none())
+                    .column(Column::initial(100.0).clip(true).resizable(true))
+                    .column(Column::initial(100.0).clip(true).resizable(true))
+                    .column(Column::initial(50.0))
+                    .column(Column::initial(100.0).clip(true).resizable(true))
+                    .column(Column::remainder().at_least(50.0))
+                    .header(row_height, |mut header| {
+                        for name in COLUMNS {
+                            header.col(|ui| {
+                                if name == "Fields" {
+                                    ui.strong(name);
+                                    return;
+                                }
+                                let arrow = match view.sort {
+                                    Some((sorted, false)) if sorted == name => " ⏶",
+                                    Some((sorted, true)) if sorted == name => " ⏷",
+                                    _ => "",
+                                };
+                                if ui
+                                    .button(RichText::new(format!("{name}{arrow}")).strong())
+                                    .on_hover_text("Sort by this column")
+                                    .clicked()
+                                {
+                                    view.sort = match view.sort {
+                                        Some((sorted, false)) if sorted == name => {
+                                            Some((sorted, true))
+                                        }
+                                        Some((sorted, true)) if sorted == name => None,
+                                        _ => Some((name, false)),
+                                    };
+                                }
+                            });
+                        }
+                    })
+                    .body(|body| {
+                        body.rows(row_height, events.len(), |mut row| {
+                            let event = events[row.index()];
+                            let color = colors.get(*event.metadata.level());
+                            row.col(|ui| {
+                                ui.label(RichText::new(event.time.to_string()).color(color));
+                            });
+                            row.col(|ui| {
+                                if ui
+                                    .add(Label::new(event.module.as_str()).sense(Sense::click()))
+                                    .on_hover_text("Open the module inspector")
+                                    .clicked()
+                                {
+                                    open_module = Some(event.module.clone());
+                                }
+                            });
+                            row.col(|ui| {
+                                ui.label(
+                                    RichText::new(event.metadata.level().as_str()).color(color),
+                                );
+                            });
+                            row.col(|ui| {
+                                ui.label(
+                                    RichText::new(event.metadata.target())
+                                        .text_style(TextStyle::Monospace)
+                                        .italics(),
+                                );
+                            });
+                            row.col(|ui| {
+                                ui.label(
+                                    RichText::new(&event.fields).text_style(TextStyle::Monospace),
+                                );
+                            });
+                        });
+                    });
+            });
+        self.show_log_view = open;
+
+        if let Some(path) = open_module {
+            self.open_inspector(path);
+        }
+    }
+}
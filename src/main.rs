@@ -3,8 +3,38 @@
 
 // When compiling natively:
 //
+// Usage: des-gui [--scenario <name>] [config.yaml]
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result {
-    use des_gui::launch_with_gui;
-    launch_with_gui(des_gui::sim::sim)
+    use des_gui::{config::GuiConfig, launch_with_config, sim};
+
+    let mut args = std::env::args().skip(1);
+    let mut path = None;
+    let mut scenario = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scenario" => scenario = Some(args.next().expect("failed to read scenario name")),
+            _ => path = Some(arg),
+        }
+    }
+
+    let mut config = match path {
+        Some(path) => GuiConfig::from_file(&path)
+            .unwrap_or_else(|e| panic!("failed to load config {path}: {e}")),
+        None => GuiConfig::default(),
+    };
+    // The command line takes precedence over the config file
+    if scenario.is_some() {
+        config.scenario = scenario;
+    }
+
+    let builder = match &config.scenario {
+        Some(name) => sim::scenario(name).unwrap_or_else(|| {
+            let known = sim::SCENARIOS.iter().map(|(n, _)| *n).collect::<Vec<_>>();
+            panic!("unknown scenario {name}, expected one of {known:?}")
+        }),
+        None => sim::sim_seeded,
+    };
+
+    launch_with_config(builder, config)
 }
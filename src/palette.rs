@@ -0,0 +1,193 @@
+use des::prelude::*;
+use egui::{Align2, Context, Key, Modifiers, ScrollArea, TextEdit, Window};
+
+use crate::{Application, RunState};
+
+/// An action that can be run from the command palette.
+#[derive(Debug, Clone)]
+pub enum Action {
+    OpenInspector(ObjectPath),
+    ExportLogs(ObjectPath),
+    ToggleModules,
+    ToggleBreakpoints,
+    ToggleGraph,
+    ToggleErrors,
+    Start,
+    Stop,
+    Step,
+    Reset,
+}
+
+#[derive(Debug, Clone)]
+pub struct Command {
+    pub name: String,
+    pub action: Action,
+}
+
+impl Command {
+    fn new(name: impl Into<String>, action: Action) -> Self {
+        Self {
+            name: name.into(),
+            action,
+        }
+    }
+
+    /// All commands available for a given sim.
+    pub fn registry(sim: &Sim<()>) -> Vec<Command> {
+        let mut commands = vec![
+            Command::new("Start", Action::Start),
+            Command::new("Stop", Action::Stop),
+            Command::new("Step", Action::Step),
+            Command::new("Reset", Action::Reset),
+            Command::new("Toggle Modules", Action::ToggleModules),
+            Command::new("Toggle Breakpoints", Action::ToggleBreakpoints),
+            Command::new("Toggle Graph", Action::ToggleGraph),
+            Command::new("Toggle Errors", Action::ToggleErrors),
+        ];
+        for path in sim.nodes() {
+            commands.push(Command::new(
+                format!("Open {path}"),
+                Action::OpenInspector(path.clone()),
+            ));
+            commands.push(Command::new(
+                format!("Export logs of {path}"),
+                Action::ExportLogs(path),
+            ));
+        }
+        commands
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CommandPalette {
+    query: String,
+    selected: usize,
+}
+
+/// Scores `name` as a case-insensitive subsequence match of `query`, where
+/// lower is better. Returns `None` if `name` does not contain the query.
+fn fuzzy_score(query: &str, name: &str) -> Option<usize> {
+    let mut chars = name.chars().map(|c| c.to_ascii_lowercase()).enumerate();
+    let mut score = 0;
+    let mut last = None;
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let q = q.to_ascii_lowercase();
+        let (i, _) = chars.find(|(_, c)| *c == q)?;
+        score += last.map_or(i, |last| i - last - 1);
+        last = Some(i);
+    }
+    Some(score)
+}
+
+impl Application {
+    pub fn render_palette(&mut self, ctx: &Context) {
+        if ctx.input_mut(|i| i.consume_key(Modifiers::COMMAND, Key::P)) {
+            self.palette = match self.palette {
+                Some(_) => None,
+                None => Some(CommandPalette::default()),
+            };
+        }
+
+        let Some(palette) = &mut self.palette else {
+            return;
+        };
+
+        let mut matches = self
+            .commands
+            .iter()
+            .filter_map(|c| Some((fuzzy_score(&palette.query, &c.name)?, c)))
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|(score, _)| *score);
+
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(Modifiers::NONE, Key::Enter),
+                i.consume_key(Modifiers::NONE, Key::Escape),
+            )
+        });
+        if escape {
+            self.palette = None;
+            return;
+        }
+        if down {
+            palette.selected += 1;
+        }
+        if up {
+            palette.selected = palette.selected.saturating_sub(1);
+        }
+        palette.selected = palette.selected.min(matches.len().saturating_sub(1));
+
+        let mut run = enter
+            .then(|| matches.get(palette.selected))
+            .flatten()
+            .map(|(_, c)| c.action.clone());
+
+        Window::new("Command Palette")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_TOP, [0.0, 40.0])
+            .show(ctx, |ui| {
+                let resp = ui.add(
+                    TextEdit::singleline(&mut palette.query)
+                        .hint_text("Type a command...")
+                        .desired_width(400.0),
+                );
+                resp.request_focus();
+                if resp.changed() {
+                    palette.selected = 0;
+                }
+
+                ui.separator();
+
+                ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for (i, (_, command)) in matches.iter().enumerate() {
+                        if ui
+                            .selectable_label(i == palette.selected, &command.name)
+                            .clicked()
+                        {
+                            run = Some(command.action.clone());
+                        }
+                    }
+                });
+            });
+
+        if let Some(action) = run {
+            self.palette = None;
+            self.execute(action);
+        }
+    }
+
+    fn execute(&mut self, action: Action) {
+        match action {
+            Action::OpenInspector(path) => self.open_inspector(path),
+            Action::ExportLogs(path) => self.export_logs(&path),
+            Action::ToggleModules => self.show_module_selection ^= true,
+            Action::ToggleBreakpoints => self.show_breakpoints ^= true,
+            Action::ToggleGraph => self.show_graph ^= true,
+            Action::ToggleErrors => self.show_errors ^= true,
+            Action::Start => self.set_run(RunState::Running),
+            Action::Stop => self.set_run(RunState::Paused),
+            Action::Step => self.set_run(RunState::Stepping(1)),
+            Action::Reset => self.reset(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_score_prefers_dense_matches() {
+        assert_eq!(fuzzy_score("", "Start"), Some(0));
+        assert_eq!(fuzzy_score("start", "Start"), Some(0));
+        assert_eq!(fuzzy_score("tg", "Toggle Graph"), Some(1));
+        assert_eq!(fuzzy_score("graph", "Toggle Breakpoints"), None);
+
+        let dense = fuzzy_score("open", "Open ping").unwrap();
+        let sparse = fuzzy_score("open", "Toggle Breakpoints").unwrap_or(usize::MAX);
+        assert!(dense < sparse);
+    }
+}
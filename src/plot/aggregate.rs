@@ -0,0 +1,245 @@
+use std::{fmt, ops::ControlFlow};
+
+use des::{net::ObjectPath, time::SimTime};
+use egui::Color32;
+use egui_plot::{PlotPoint, PlotPoints};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use serde_norway::Value;
+
+use super::{Aggregation, Coalesce, Guard, Tracer, TreeTracer, access};
+use crate::{channel::is_channel_path, config::TraceConfig};
+
+/// How the values of all matching modules are combined into one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Combine {
+    Sum,
+    Mean,
+    Max,
+}
+
+impl Combine {
+    fn apply(&self, ys: &[f64]) -> Option<f64> {
+        if ys.is_empty() {
+            return None;
+        }
+        Some(match self {
+            Self::Sum => ys.iter().sum(),
+            Self::Mean => ys.iter().sum::<f64>() / ys.len() as f64,
+            Self::Max => ys.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        })
+    }
+}
+
+impl fmt::Display for Combine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sum => write!(f, "sum"),
+            Self::Mean => write!(f, "mean"),
+            Self::Max => write!(f, "max"),
+        }
+    }
+}
+
+/// Whether `path` matches `pattern`, where `*` matches any run of
+/// characters within one path segment, e.g. `net.worker*`.
+pub fn matches_pattern(pattern: &str, path: &str) -> bool {
+    pattern.split('.').count() == path.split('.').count()
+        && pattern
+            .split('.')
+            .zip(path.split('.'))
+            .all(|(pattern, segment)| matches_segment(pattern, segment))
+}
+
+fn matches_segment(pattern: &str, segment: &str) -> bool {
+    let Some((head, rest)) = pattern.split_once('*') else {
+        return pattern == segment;
+    };
+    let Some(mut path) = segment.strip_prefix(head) else {
+        return false;
+    };
+
+    let mut parts = rest.split('*').peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            return path.len() >= part.len() && path.ends_with(part);
+        }
+        match path.find(part) {
+            Some(i) => path = &path[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// The pattern matching `path` and all its siblings, i.e. all modules
+/// with the same parent, or all root modules.
+pub fn siblings_pattern(path: &ObjectPath) -> String {
+    match path.as_str().rsplit_once('.') {
+        Some((parent, _)) => format!("{parent}.*"),
+        None => "*".to_string(),
+    }
+}
+
+/// One key of many modules, combined into a single line, e.g. the total
+/// queue length of all workers.
+///
+/// All observed modules matching the pattern are combined on each update,
+/// so the matching modules must be observed when the tracer is created.
+pub struct AggregateTracer {
+    pattern: String,
+    combine: Combine,
+    // Records the combined values, the path of which is the pattern
+    inner: TreeTracer,
+}
+
+impl AggregateTracer {
+    pub fn new(pattern: String, key: String, combine: Combine) -> Self {
+        Self {
+            inner: TreeTracer::new(ObjectPath::from(pattern.as_str()), key),
+            pattern,
+            combine,
+        }
+    }
+}
+
+impl Tracer for AggregateTracer {
+    fn name(&self) -> String {
+        format!("{} of {} {}", self.combine, self.pattern, self.inner.key)
+    }
+
+    fn color(&self) -> Color32 {
+        self.inner.color()
+    }
+
+    fn needs_path(&self, path: &ObjectPath) -> bool {
+        !is_channel_path(path) && matches_pattern(&self.pattern, path.as_str())
+    }
+
+    fn is_stale(&self) -> bool {
+        self.inner.is_stale()
+    }
+
+    fn update(&mut self, values: &FxHashMap<ObjectPath, Value>) -> ControlFlow<()> {
+        let mut resolved = false;
+        let ys = values
+            .iter()
+            .filter(|(path, _)| self.needs_path(path))
+            .inspect(|_| resolved = true)
+            .filter_map(|(_, map)| access(map, &self.inner.key)?.as_f64())
+            .collect::<Vec<_>>();
+        let value = self.combine.apply(&ys).map(Value::from);
+        self.inner.record(resolved, value)
+    }
+
+    fn points(&self) -> PlotPoints<'_> {
+        self.inner.points()
+    }
+
+    fn raw_points(&self) -> &[PlotPoint] {
+        self.inner.raw_points()
+    }
+
+    fn config(&self) -> TraceConfig {
+        TraceConfig {
+            combine: Some(self.combine),
+            ..self.inner.config()
+        }
+    }
+
+    fn band(&self) -> Option<PlotPoints<'_>> {
+        self.inner.band()
+    }
+
+    fn unit(&self) -> Option<&str> {
+        self.inner.unit()
+    }
+
+    fn aggregation(&mut self) -> Option<&mut Option<Aggregation>> {
+        self.inner.aggregation()
+    }
+
+    fn coalesce(&mut self) -> Option<&mut Option<Coalesce>> {
+        self.inner.coalesce()
+    }
+
+    fn guard(&mut self) -> Option<&mut Option<Guard>> {
+        self.inner.guard()
+    }
+
+    fn tripped(&self) -> Option<&str> {
+        self.inner.tripped()
+    }
+
+    fn truncate(&mut self, time: SimTime) {
+        self.inner.truncate(time);
+    }
+
+    fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    fn restore_points(&mut self, points: Vec<PlotPoint>) {
+        self.inner.restore_points(points);
+    }
+
+    fn max_points(&mut self) -> Option<&mut Option<usize>> {
+        self.inner.max_points()
+    }
+
+    fn into_inner(self: Box<Self>) -> Box<dyn Tracer> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_norway::Mapping;
+
+    use super::*;
+
+    #[test]
+    fn glob_patterns() {
+        assert!(matches_pattern("net.*", "net.worker1"));
+        assert!(matches_pattern("net.worker*.queue", "net.worker12.queue"));
+        assert!(matches_pattern("*", "ping"));
+        assert!(matches_pattern("ping", "ping"));
+        assert!(!matches_pattern("net.*", "other.worker1"));
+        assert!(!matches_pattern("*a*a", "a"));
+        assert_eq!(siblings_pattern(&ObjectPath::from("net.worker1")), "net.*");
+    }
+
+    #[test]
+    fn glob_stays_within_a_segment() {
+        let siblings = siblings_pattern(&ObjectPath::from("net.worker1"));
+        assert!(matches_pattern(&siblings, "net.worker2"));
+        assert!(!matches_pattern(&siblings, "net.worker1.queue"));
+        assert!(!matches_pattern("net.worker*", "net.worker1.queue"));
+
+        let roots = siblings_pattern(&ObjectPath::from("ping"));
+        assert_eq!(roots, "*");
+        assert!(matches_pattern(&roots, "pong"));
+        assert!(!matches_pattern(&roots, "net.worker1"));
+    }
+
+    #[test]
+    fn combines_matching_modules() {
+        let mut values = FxHashMap::default();
+        for (path, queue) in [("net.a", 1), ("net.b", 4), ("other", 100)] {
+            let module = Mapping::from_iter([(Value::from("queue"), Value::from(queue))]);
+            values.insert(ObjectPath::from(path), Value::Mapping(module));
+        }
+
+        for (combine, y) in [
+            (Combine::Sum, 5.0),
+            (Combine::Mean, 2.5),
+            (Combine::Max, 4.0),
+        ] {
+            let mut tracer =
+                AggregateTracer::new("net.*".to_string(), "queue".to_string(), combine);
+            let _ = tracer.update(&values);
+            assert_eq!(tracer.raw_points()[0].y, y);
+            assert!(!tracer.is_stale());
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::time::Duration;
+
+use egui::{ComboBox, DragValue, Ui};
+use egui_plot::PlotPoint;
+
+/// How the samples within one window are reduced to a displayed point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reducer {
+    Mean,
+    Min,
+    Max,
+    /// The mean, with a shaded band between min and max.
+    MinMax,
+}
+
+/// How samples that share the same x (e.g. several events at the same
+/// `SimTime`) are merged into a single step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coalesce {
+    Last,
+    Mean,
+    Min,
+    Max,
+}
+
+impl Coalesce {
+    fn reduce(&self, ys: &[f64]) -> f64 {
+        match self {
+            Self::Last => ys[ys.len() - 1],
+            Self::Mean => ys.iter().sum::<f64>() / ys.len() as f64,
+            Self::Min => ys.iter().copied().fold(f64::INFINITY, f64::min),
+            Self::Max => ys.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+
+    /// Merges samples at an identical x into one value, keeping a clean
+    /// stepped series without degenerate vertical segments.
+    pub fn apply(&self, values: &[PlotPoint]) -> Vec<PlotPoint> {
+        let mut out = Vec::<PlotPoint>::with_capacity(values.len());
+        for group in values.chunk_by(|l, r| l.x == r.x) {
+            let x = group[0].x;
+
+            // The first point of a stepped group repeats the previous value.
+            let samples = match out.last() {
+                Some(_) if group.len() > 1 => &group[1..],
+                _ => group,
+            };
+            let mut ys = samples.iter().map(|p| p.y).collect::<Vec<_>>();
+            ys.dedup();
+            let y = self.reduce(&ys);
+
+            match out.last().map(|p| p.y) {
+                Some(last_y) if last_y == y => {}
+                Some(last_y) => {
+                    out.push(PlotPoint { x, y: last_y });
+                    out.push(PlotPoint { x, y });
+                }
+                None => out.push(PlotPoint { x, y }),
+            }
+        }
+        out
+    }
+}
+
+pub fn show_coalesce(ui: &mut Ui, id: impl std::hash::Hash, coalesce: &mut Option<Coalesce>) {
+    ComboBox::new(id, "")
+        .selected_text(match coalesce {
+            Some(coalesce) => format!("Coalesce {coalesce:?}"),
+            None => "No coalescing".to_string(),
+        })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(coalesce, Some(Coalesce::Last), "Last");
+            ui.selectable_value(coalesce, Some(Coalesce::Mean), "Mean");
+            ui.selectable_value(coalesce, Some(Coalesce::Min), "Min");
+            ui.selectable_value(coalesce, Some(Coalesce::Max), "Max");
+            ui.selectable_value(coalesce, None, "Off");
+        });
+}
+
+/// A user-chosen aggregation, e.g. "1 point per 10ms, showing the mean".
+///
+/// Only applied when displaying a trace, the raw samples stay untouched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aggregation {
+    pub window: Duration,
+    pub reducer: Reducer,
+}
+
+impl Default for Aggregation {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_millis(10),
+            reducer: Reducer::Mean,
+        }
+    }
+}
+
+struct Bucket {
+    x: f64,
+    sum: f64,
+    n: usize,
+    min: f64,
+    max: f64,
+}
+
+impl Aggregation {
+    fn buckets(&self, values: &[PlotPoint]) -> Vec<Bucket> {
+        let window = self.window.as_secs_f64();
+        let mut buckets = Vec::<Bucket>::new();
+        for p in values {
+            let x = (p.x / window).floor() * window + window / 2.0;
+            match buckets.last_mut() {
+                Some(bucket) if bucket.x == x => {
+                    bucket.sum += p.y;
+                    bucket.n += 1;
+                    bucket.min = bucket.min.min(p.y);
+                    bucket.max = bucket.max.max(p.y);
+                }
+                _ => buckets.push(Bucket {
+                    x,
+                    sum: p.y,
+                    n: 1,
+                    min: p.y,
+                    max: p.y,
+                }),
+            }
+        }
+        buckets
+    }
+
+    pub fn apply(&self, values: &[PlotPoint]) -> Vec<PlotPoint> {
+        if self.window.is_zero() {
+            return values.to_vec();
+        }
+
+        self.buckets(values)
+            .into_iter()
+            .map(|b| PlotPoint {
+                x: b.x,
+                y: match self.reducer {
+                    Reducer::Mean | Reducer::MinMax => b.sum / b.n as f64,
+                    Reducer::Min => b.min,
+                    Reducer::Max => b.max,
+                },
+            })
+            .collect()
+    }
+
+    /// The outline of the min/max band, if the reducer asks for one.
+    pub fn band(&self, values: &[PlotPoint]) -> Option<Vec<PlotPoint>> {
+        if self.reducer != Reducer::MinMax || self.window.is_zero() {
+            return None;
+        }
+
+        let buckets = self.buckets(values);
+        let upper = buckets.iter().map(|b| PlotPoint { x: b.x, y: b.max });
+        let lower = buckets.iter().rev().map(|b| PlotPoint { x: b.x, y: b.min });
+        Some(upper.chain(lower).collect())
+    }
+}
+
+pub fn show_aggregation(
+    ui: &mut Ui,
+    id: impl std::hash::Hash,
+    aggregation: &mut Option<Aggregation>,
+) {
+    let mut enabled = aggregation.is_some();
+    ui.checkbox(&mut enabled, "Aggregate");
+    if !enabled {
+        *aggregation = None;
+        return;
+    }
+
+    let aggregation = aggregation.get_or_insert_with(Aggregation::default);
+    let mut window = aggregation.window.as_secs_f64() * 1000.0;
+    ui.add(
+        DragValue::new(&mut window)
+            .range(1.0..=60_000.0)
+            .suffix(" ms"),
+    );
+    aggregation.window = Duration::from_secs_f64(window / 1000.0);
+
+    ComboBox::new(id, "")
+        .selected_text(format!("{:?}", aggregation.reducer))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut aggregation.reducer, Reducer::Mean, "Mean");
+            ui.selectable_value(&mut aggregation.reducer, Reducer::Min, "Min");
+            ui.selectable_value(&mut aggregation.reducer, Reducer::Max, "Max");
+            ui.selectable_value(&mut aggregation.reducer, Reducer::MinMax, "MinMax");
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(values: &[(f64, f64)]) -> Vec<PlotPoint> {
+        values.iter().map(|&(x, y)| PlotPoint { x, y }).collect()
+    }
+
+    fn pairs(values: &[PlotPoint]) -> Vec<(f64, f64)> {
+        values.iter().map(|p| (p.x, p.y)).collect()
+    }
+
+    #[test]
+    fn coalesce_same_timestamp_updates() {
+        // stepped output of 1 -> 2 -> 3 -> 4 at x=1.0, followed by 5 at x=2.0
+        let raw = points(&[
+            (0.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 2.0),
+            (1.0, 2.0),
+            (1.0, 3.0),
+            (1.0, 3.0),
+            (1.0, 4.0),
+            (2.0, 4.0),
+            (2.0, 5.0),
+        ]);
+
+        assert_eq!(
+            pairs(&Coalesce::Last.apply(&raw)),
+            [(0.0, 1.0), (1.0, 1.0), (1.0, 4.0), (2.0, 4.0), (2.0, 5.0)]
+        );
+        assert_eq!(
+            pairs(&Coalesce::Mean.apply(&raw)),
+            [(0.0, 1.0), (1.0, 1.0), (1.0, 3.0), (2.0, 3.0), (2.0, 5.0)]
+        );
+        assert_eq!(
+            pairs(&Coalesce::Min.apply(&raw)),
+            [(0.0, 1.0), (1.0, 1.0), (1.0, 2.0), (2.0, 2.0), (2.0, 5.0)]
+        );
+    }
+
+    #[test]
+    fn coalesce_initial_group() {
+        let raw = points(&[(0.0, 1.0), (0.0, 1.0), (0.0, 2.0)]);
+        assert_eq!(pairs(&Coalesce::Last.apply(&raw)), [(0.0, 2.0)]);
+    }
+}
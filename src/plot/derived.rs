@@ -0,0 +1,270 @@
+use std::{fmt, ops::ControlFlow};
+
+use des::{net::ObjectPath, time::SimTime};
+use egui::{Color32, ComboBox, Id, Ui};
+use egui_plot::{PlotPoint, PlotPoints};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use serde_norway::Value;
+
+use super::{Aggregation, Coalesce, Guard, Tracer, TreeTracer, access};
+use crate::{
+    TreeTraceReq,
+    config::{DeriveConfig, TraceConfig},
+};
+
+/// How the values of two traces are combined into a derived one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Op {
+    const ALL: [Self; 4] = [Self::Add, Self::Sub, Self::Mul, Self::Div];
+
+    fn apply(&self, lhs: f64, rhs: f64) -> Option<f64> {
+        match self {
+            Self::Add => Some(lhs + rhs),
+            Self::Sub => Some(lhs - rhs),
+            Self::Mul => Some(lhs * rhs),
+            // A gap in the line, rather than an infinite value
+            Self::Div if rhs == 0.0 => None,
+            Self::Div => Some(lhs / rhs),
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Add => write!(f, "+"),
+            Self::Sub => write!(f, "-"),
+            Self::Mul => write!(f, "*"),
+            Self::Div => write!(f, "/"),
+        }
+    }
+}
+
+/// Two keys combined into a single line over time, e.g. sent - received.
+///
+/// Both keys are sampled from the observed values on each update, thus
+/// the derived line does not depend on the traces it was created from.
+pub struct DerivedTracer {
+    rhs: TreeTraceReq,
+    op: Op,
+    // Records the derived values, the path and key of which are the lhs
+    inner: TreeTracer,
+}
+
+impl DerivedTracer {
+    pub fn new(lhs: TreeTraceReq, op: Op, rhs: TreeTraceReq) -> Self {
+        Self {
+            inner: TreeTracer::new(lhs.0, lhs.1),
+            rhs,
+            op,
+        }
+    }
+
+    fn sample(values: &FxHashMap<ObjectPath, Value>, path: &ObjectPath, key: &str) -> Option<f64> {
+        access(values.get(path)?, key)?.as_f64()
+    }
+}
+
+impl Tracer for DerivedTracer {
+    fn name(&self) -> String {
+        format!(
+            "{} {} {} {} {}",
+            self.inner.path, self.inner.key, self.op, self.rhs.0, self.rhs.1
+        )
+    }
+
+    fn color(&self) -> Color32 {
+        self.inner.color()
+    }
+
+    fn needs_path(&self, path: &ObjectPath) -> bool {
+        self.inner.path == *path || self.rhs.0 == *path
+    }
+
+    fn is_stale(&self) -> bool {
+        self.inner.is_stale()
+    }
+
+    fn update(&mut self, values: &FxHashMap<ObjectPath, Value>) -> ControlFlow<()> {
+        let resolved = values.contains_key(&self.inner.path) && values.contains_key(&self.rhs.0);
+        let lhs = Self::sample(values, &self.inner.path, &self.inner.key);
+        let rhs = Self::sample(values, &self.rhs.0, &self.rhs.1);
+        let value = lhs
+            .zip(rhs)
+            .and_then(|(lhs, rhs)| self.op.apply(lhs, rhs))
+            .map(Value::from);
+        self.inner.record(resolved, value)
+    }
+
+    fn points(&self) -> PlotPoints<'_> {
+        self.inner.points()
+    }
+
+    fn raw_points(&self) -> &[PlotPoint] {
+        self.inner.raw_points()
+    }
+
+    fn config(&self) -> TraceConfig {
+        TraceConfig {
+            derive: Some(DeriveConfig {
+                op: self.op,
+                path: self.rhs.0.to_string(),
+                key: self.rhs.1.clone(),
+            }),
+            ..self.inner.config()
+        }
+    }
+
+    fn band(&self) -> Option<PlotPoints<'_>> {
+        self.inner.band()
+    }
+
+    fn aggregation(&mut self) -> Option<&mut Option<Aggregation>> {
+        self.inner.aggregation()
+    }
+
+    fn coalesce(&mut self) -> Option<&mut Option<Coalesce>> {
+        self.inner.coalesce()
+    }
+
+    fn guard(&mut self) -> Option<&mut Option<Guard>> {
+        self.inner.guard()
+    }
+
+    fn tripped(&self) -> Option<&str> {
+        self.inner.tripped()
+    }
+
+    fn truncate(&mut self, time: SimTime) {
+        self.inner.truncate(time);
+    }
+
+    fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    fn restore_points(&mut self, points: Vec<PlotPoint>) {
+        self.inner.restore_points(points);
+    }
+
+    fn max_points(&mut self) -> Option<&mut Option<usize>> {
+        self.inner.max_points()
+    }
+
+    fn into_inner(self: Box<Self>) -> Box<dyn Tracer> {
+        self
+    }
+}
+
+/// The traces picked to be combined, kept in the egui memory.
+#[derive(Debug, Clone, Copy)]
+struct DeriveForm {
+    lhs: usize,
+    op: Op,
+    rhs: usize,
+}
+
+/// A form to combine two plain traces of a plot group into a derived one.
+/// Returns the config of the derived trace, once confirmed.
+pub fn show_derive(ui: &mut Ui, group: usize, plot: &[Box<dyn Tracer>]) -> Option<TraceConfig> {
+    // Only traces of a single key over time can be derived from
+    let sources = plot
+        .iter()
+        .map(|t| t.config())
+        .filter(|c| c.vs.is_none() && !c.sequence && c.combine.is_none() && c.derive.is_none())
+        .collect::<Vec<_>>();
+    if sources.len() < 2 {
+        return None;
+    }
+
+    let id = Id::new(("derive", group));
+    let mut form = ui.data_mut(|d| {
+        *d.get_temp_mut_or_insert_with(id, || DeriveForm {
+            lhs: 0,
+            op: Op::Sub,
+            rhs: 1,
+        })
+    });
+    form.lhs = form.lhs.min(sources.len() - 1);
+    form.rhs = form.rhs.min(sources.len() - 1);
+
+    let label = |c: &TraceConfig| format!("{} {}", c.path, c.key);
+    let mut derived = None;
+    ui.horizontal(|ui| {
+        ComboBox::from_id_salt(("derive-lhs", group))
+            .selected_text(label(&sources[form.lhs]))
+            .show_index(ui, &mut form.lhs, sources.len(), |i| label(&sources[i]));
+        ComboBox::from_id_salt(("derive-op", group))
+            .width(30.0)
+            .selected_text(form.op.to_string())
+            .show_ui(ui, |ui| {
+                for op in Op::ALL {
+                    ui.selectable_value(&mut form.op, op, op.to_string());
+                }
+            });
+        ComboBox::from_id_salt(("derive-rhs", group))
+            .selected_text(label(&sources[form.rhs]))
+            .show_index(ui, &mut form.rhs, sources.len(), |i| label(&sources[i]));
+        if ui
+            .button("Derive")
+            .on_hover_text("Adds a trace computed from the two, to this group")
+            .clicked()
+        {
+            let (lhs, rhs) = (&sources[form.lhs], &sources[form.rhs]);
+            derived = Some(TraceConfig {
+                derive: Some(DeriveConfig {
+                    op: form.op,
+                    path: rhs.path.clone(),
+                    key: rhs.key.clone(),
+                }),
+                max_points: None,
+                smooth: None,
+                ..lhs.clone()
+            });
+        }
+    });
+    ui.data_mut(|d| d.insert_temp(id, form));
+    derived
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_norway::Mapping;
+
+    use super::*;
+
+    #[test]
+    fn derives_from_two_keys() {
+        let module = Mapping::from_iter([
+            (Value::from("sent"), Value::from(10)),
+            (Value::from("received"), Value::from(4)),
+        ]);
+        let values = FxHashMap::from_iter([(ObjectPath::from("net"), Value::Mapping(module))]);
+        let key = |k: &str| (ObjectPath::from("net"), k.to_string());
+
+        for (op, y) in [
+            (Op::Sub, 6.0),
+            (Op::Div, 2.5),
+            (Op::Add, 14.0),
+            (Op::Mul, 40.0),
+        ] {
+            let mut tracer = DerivedTracer::new(key("sent"), op, key("received"));
+            let _ = tracer.update(&values);
+            assert_eq!(tracer.raw_points()[0].y, y);
+            assert!(!tracer.is_stale());
+        }
+
+        let mut tracer = DerivedTracer::new(key("sent"), Op::Div, key("missing"));
+        let _ = tracer.update(&values);
+        assert!(tracer.raw_points().is_empty());
+        assert_eq!(Op::Div.apply(1.0, 0.0), None);
+    }
+}
@@ -0,0 +1,60 @@
+use egui::{Checkbox, DragValue, Ui};
+
+/// Stops the sim once a traced value becomes NaN, infinite or, if a bound
+/// is set, exceeds it in magnitude.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Guard {
+    pub bound: Option<f64>,
+}
+
+impl Guard {
+    /// Returns why `y` violates the guard, if it does.
+    pub fn check(&self, y: f64) -> Option<String> {
+        if y.is_nan() {
+            Some("value is NaN".to_string())
+        } else if y.is_infinite() {
+            Some(format!("value is {y}"))
+        } else {
+            self.bound
+                .filter(|bound| y.abs() > *bound)
+                .map(|bound| format!("|{y}| exceeds {bound}"))
+        }
+    }
+}
+
+pub fn show_guard(ui: &mut Ui, guard: &mut Option<Guard>) {
+    let mut enabled = guard.is_some();
+    ui.add(Checkbox::new(&mut enabled, "Guard"))
+        .on_hover_text("Pause on NaN, infinite values or values beyond a bound");
+    if !enabled {
+        *guard = None;
+        return;
+    }
+
+    let guard = guard.get_or_insert_with(Guard::default);
+    let mut bounded = guard.bound.is_some();
+    ui.checkbox(&mut bounded, "|y| <");
+    if bounded {
+        let bound = guard.bound.get_or_insert(1e9);
+        ui.add(DragValue::new(bound).speed(1.0).range(0.0..=f64::MAX));
+    } else {
+        guard.bound = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guard_detects_nan_and_bounds() {
+        let guard = Guard::default();
+        assert!(guard.check(f64::NAN).is_some());
+        assert!(guard.check(f64::NEG_INFINITY).is_some());
+        assert!(guard.check(1e300).is_none());
+
+        let guard = Guard { bound: Some(10.0) };
+        assert!(guard.check(-10.0).is_none());
+        assert!(guard.check(-10.5).is_some());
+    }
+}
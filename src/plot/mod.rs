@@ -1,10 +1,41 @@
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufWriter, Write},
+    ops::ControlFlow,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
 use des::{net::ObjectPath, time::SimTime};
-use egui::{Context, ScrollArea, SidePanel, panel::Side};
-use egui_plot::{Legend, Line, Plot, PlotPoint, PlotPoints};
-use fxhash::FxHashMap;
+use egui::{Color32, Context, DragValue, RichText, ScrollArea, SidePanel, panel::Side};
+use egui_file_dialog::FileDialog;
+use egui_plot::{
+    Bar, BarChart, Legend, Line, MarkerShape, Plot, PlotPoint, PlotPoints, Points, Polygon, VLine,
+};
+use fxhash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
 use serde_norway::Value;
 
-use crate::Application;
+use crate::{Application, config::TraceConfig, tracing::GuiTracingObserver};
+
+mod aggregate;
+mod aggregation;
+mod derived;
+mod guard;
+mod screenshot;
+mod sequence;
+mod smoothed;
+mod xy;
+
+pub use aggregate::{AggregateTracer, Combine, matches_pattern, siblings_pattern};
+use aggregation::{Aggregation, Coalesce};
+pub use derived::{DerivedTracer, Op};
+use guard::Guard;
+pub use screenshot::PlotScreenshot;
+pub use sequence::SequenceTracer;
+use smoothed::SmoothedTracer;
+pub use xy::XYTracer;
 
 impl Application {
     pub fn show_plot(&mut self, ctx: &Context) {
@@ -12,19 +43,409 @@ impl Application {
             self.traces.pop();
         }
 
+        let mut export = None;
+        let mut image = None;
+        let mut derive = None;
+        // The hovered time of the last frame, read out below every plot
+        let cursor = self.plot_cursor;
+        let mut hovered = None;
         SidePanel::new(Side::Right, "plot").show(ctx, |ui| {
+            if ui
+                .button("Export All Traces (tidy CSV)")
+                .on_hover_text("Writes traces.csv with columns time,series,value")
+                .clicked()
+                && let Err(e) = self.export_traces()
+            {
+                ::tracing::error!("failed to export traces: {e}");
+            }
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Add Marker")
+                    .on_hover_text("Marks the current sim time on all plots")
+                    .clicked()
+                {
+                    self.markers.push(Marker {
+                        time: SimTime::now(),
+                        label: format!("marker {}", self.markers.len() + 1),
+                    });
+                }
+                if !self.markers.is_empty() && ui.button("Clear Markers").clicked() {
+                    self.markers.clear();
+                }
+            });
+
             ScrollArea::vertical().show(ui, |ui| {
-                for (i, plot) in self.traces.iter().enumerate() {
-                    Plot::new(format!("plot-{}", i))
+                for i in 0..self.traces.len() {
+                    let plot = &self.traces[i];
+
+                    // Isolation is a view-only filter, ignore it if the trace
+                    // was moved to another group.
+                    let isolated = self
+                        .isolated
+                        .get(&i)
+                        .filter(|name| plot.iter().any(|t| t.name() == **name))
+                        .cloned();
+
+                    let mut log = self.log_scale.contains(&i);
+                    if ui
+                        .checkbox(&mut log, "Log scale")
+                        .on_hover_text("Values <= 0 are not shown")
+                        .changed()
+                    {
+                        if log {
+                            self.log_scale.insert(i);
+                        } else {
+                            self.log_scale.remove(&i);
+                        }
+                    }
+
+                    let mut fixed = self.y_ranges.get(&i).copied();
+                    let mut refit = false;
+                    ui.horizontal(|ui| {
+                        let mut lock = fixed.is_some();
+                        refit |= ui
+                            .checkbox(&mut lock, "Fixed y range")
+                            .on_hover_text(
+                                "Keeps spikes from rescaling the plot, double click to return",
+                            )
+                            .changed();
+                        if lock {
+                            // The extent is computed once, when the range gets locked
+                            let range = fixed.get_or_insert_with(|| y_extent(plot));
+                            refit |= ui
+                                .add(DragValue::new(&mut range.0).prefix("min ").speed(0.1))
+                                .changed();
+                            refit |= ui
+                                .add(DragValue::new(&mut range.1).prefix("max ").speed(0.1))
+                                .changed();
+                        } else {
+                            fixed = None;
+                        }
+                    });
+                    match fixed {
+                        Some(range) => self.y_ranges.insert(i, range),
+                        None => self.y_ranges.remove(&i),
+                    };
+
+                    let mut overlay = self.log_overlay.contains(&i);
+                    if ui
+                        .checkbox(&mut overlay, "Show logs")
+                        .on_hover_text("Marks when traced modules logged, hover for the message")
+                        .changed()
+                    {
+                        if overlay {
+                            self.log_overlay.insert(i);
+                        } else {
+                            self.log_overlay.remove(&i);
+                        }
+                    }
+
+                    // Only label the axis if all traces agree on the unit
+                    let unit = plot
+                        .first()
+                        .and_then(|t| t.unit())
+                        .filter(|unit| plot.iter().all(|t| t.unit() == Some(unit)))
+                        .map(str::to_string);
+
+                    let integers = plot
+                        .iter()
+                        .filter(|t| t.is_integer())
+                        .map(|t| t.name())
+                        .collect::<FxHashSet<_>>();
+                    let integer = !plot.is_empty() && integers.len() == plot.len();
+
+                    // Markers and the cursor are sim times, meaningless on
+                    // other x axes
+                    let timed = plot.iter().any(|t| t.style() == TraceStyle::Line);
+
+                    let mut p = Plot::new(format!("plot-{}", i))
                         .legend(Legend::default())
                         .view_aspect(2.0)
-                        .show(ui, |ui| {
-                            for trace in plot {
-                                let line = Line::new(trace.points()).name(trace.name());
+                        .label_formatter(move |name, point| {
+                            let y = match log {
+                                true => 10f64.powf(point.y),
+                                false => point.y,
+                            };
+                            let y = format_y(y, integers.contains(name));
+                            match name.is_empty() {
+                                true => format!("x = {:.3}\ny = {y}", point.x),
+                                false => format!("{name}\nx = {:.3}\ny = {y}", point.x),
+                            }
+                        });
+                    match unit {
+                        Some(unit) => {
+                            p = p
+                                .y_axis_label(unit.clone())
+                                .y_axis_formatter(move |mark, _| {
+                                    let value = match log {
+                                        true => 10f64.powf(mark.value),
+                                        false => mark.value,
+                                    };
+                                    format_si(value, &unit)
+                                });
+                        }
+                        None if log => {
+                            p = p.y_axis_formatter(|mark, _| {
+                                format!("{:e}", 10f64.powf(mark.value))
+                            });
+                        }
+                        // Fractional ticks are meaningless for counters
+                        None if integer => {
+                            p = p.y_axis_formatter(|mark, _| match mark.value.fract() == 0.0 {
+                                true => format_y(mark.value, true),
+                                false => String::new(),
+                            });
+                        }
+                        None => {}
+                    }
+                    // Only the initial bounds, thus panning still works and
+                    // x follows the latest time until panned
+                    if let Some((min, max)) = fixed.and_then(|range| y_bounds(range, log)) {
+                        p = p.default_y_bounds(min, max);
+                    }
+                    if refit {
+                        p = p.reset();
+                    }
+                    if timed {
+                        p = p
+                            .link_cursor(TIME_AXIS_GROUP, [true, false])
+                            .link_axis(TIME_AXIS_GROUP, [true, false]);
+                    }
+                    let annotations = plot
+                        .iter()
+                        .filter(|_| overlay)
+                        .filter(|t| {
+                            t.style() == TraceStyle::Line
+                                && isolated.as_ref().is_none_or(|name| *name == t.name())
+                                && !self.hidden.contains(&t.name())
+                        })
+                        .filter_map(|t| {
+                            let module = t.module()?;
+                            let points = log_points(&self.logs, module, t.raw_points(), log);
+                            Some((t.color(), module.clone(), points))
+                        })
+                        .collect::<Vec<_>>();
+                    let markers = &self.markers;
+                    let hidden = &self.hidden;
+                    let shown = p.show(ui, |ui| {
+                        for (color, _, points) in &annotations {
+                            let points = points.iter().map(|(p, _)| [p.x, p.y]).collect::<Vec<_>>();
+                            ui.points(
+                                Points::new(points)
+                                    .color(*color)
+                                    .shape(MarkerShape::Diamond)
+                                    .radius(3.0),
+                            );
+                        }
+                        for marker in markers.iter().filter(|_| timed) {
+                            ui.vline(
+                                VLine::new(marker.time.as_secs_f64())
+                                    .name(&marker.label)
+                                    .color(Color32::GRAY),
+                            );
+                        }
+                        for trace in plot {
+                            if isolated.as_ref().is_some_and(|name| *name != trace.name())
+                                || hidden.contains(&trace.name())
+                            {
+                                continue;
+                            }
+                            if let Some(inner) = trace.inner() {
+                                let line = Line::new(scale(inner.points(), log))
+                                    .name(inner.name())
+                                    .color(inner.color().gamma_multiply(0.4));
                                 ui.line(line);
                             }
+                            if let Some(band) = trace.band() {
+                                ui.polygon(
+                                    Polygon::new(scale(band, log))
+                                        .name(trace.name())
+                                        .fill_color(trace.color().gamma_multiply(0.25)),
+                                );
+                            }
+                            let points = scale(trace.points(), log);
+                            match trace.style() {
+                                TraceStyle::Line => {
+                                    let line =
+                                        Line::new(points).name(trace.name()).color(trace.color());
+                                    ui.line(line);
+                                }
+                                TraceStyle::Scatter => {
+                                    let points = Points::new(points)
+                                        .name(trace.name())
+                                        .color(trace.color())
+                                        .radius(2.0);
+                                    ui.points(points);
+                                }
+                                TraceStyle::Bars => {
+                                    let bars = points
+                                        .points()
+                                        .iter()
+                                        .map(|p| Bar::new(p.x, p.y))
+                                        .collect();
+                                    let chart =
+                                        BarChart::new(bars).name(trace.name()).color(trace.color());
+                                    ui.bar_chart(chart);
+                                }
+                            }
+                        }
+                    });
+                    self.plot_image.capture(ctx, i, shown.response.rect);
+                    if let Some(pos) = shown.response.hover_pos()
+                        && let Some((_, module, idx)) = annotations
+                            .iter()
+                            .flat_map(|(_, module, points)| {
+                                points.iter().map(move |(p, idx)| (module, p, *idx))
+                            })
+                            .map(|(module, p, idx)| {
+                                (
+                                    shown.transform.position_from_point(p).distance(pos),
+                                    module,
+                                    idx,
+                                )
+                            })
+                            .filter(|(distance, _, _)| *distance < LOG_HOVER_DISTANCE)
+                            .min_by(|l, r| l.0.total_cmp(&r.0))
+                        && let Some(message) = log_message(&self.logs, module, idx)
+                    {
+                        shown.response.clone().on_hover_text_at_pointer(message);
+                    }
+                    if timed && let Some(pos) = shown.response.hover_pos() {
+                        hovered = Some(shown.transform.value_from_position(pos).x);
+                    }
+
+                    let mut smooth = None;
+                    ui.horizontal_wrapped(|ui| {
+                        for (j, trace) in plot.iter().enumerate() {
+                            let name = trace.name();
+                            let selected = isolated.as_ref() == Some(&name);
+                            let mut visible = !self.hidden.contains(&name);
+                            if ui
+                                .checkbox(&mut visible, "")
+                                .on_hover_text("Hidden traces keep recording")
+                                .changed()
+                            {
+                                match visible {
+                                    true => self.hidden.remove(&name),
+                                    false => self.hidden.insert(name.clone()),
+                                };
+                            }
+                            let label = match cursor.filter(|_| timed) {
+                                Some(x) => match value_at(trace.raw_points(), x) {
+                                    Some(y) => {
+                                        format!("{name} = {}", format_y(y, trace.is_integer()))
+                                    }
+                                    None => name.clone(),
+                                },
+                                None => name.clone(),
+                            };
+                            let resp = ui
+                                .selectable_label(
+                                    selected,
+                                    RichText::new(label).color(trace.color()),
+                                )
+                                .on_hover_text(
+                                    "Click to show only this series, right click to smooth",
+                                );
+                            resp.context_menu(|ui| {
+                                for window in [5, 10, 50] {
+                                    if ui.button(format!("Smooth (avg {window})")).clicked() {
+                                        smooth = Some((j, Some(window)));
+                                        ui.close_menu();
+                                    }
+                                }
+                                if trace.inner().is_some()
+                                    && ui.button("Remove smoothing").clicked()
+                                {
+                                    smooth = Some((j, None));
+                                    ui.close_menu();
+                                }
+                            });
+                            if resp.clicked() {
+                                if selected {
+                                    self.isolated.remove(&i);
+                                } else {
+                                    self.isolated.insert(i, name);
+                                }
+                                return;
+                            }
+                        }
+                        if ui.small_button("Export CSV").clicked() {
+                            export = Some(i);
+                        }
+                        if ui
+                            .small_button("Save Image")
+                            .on_hover_text("Saves the plot as shown, as a PNG")
+                            .clicked()
+                        {
+                            image = Some(i);
+                        }
+                    });
+                    if let Some((j, window)) = smooth {
+                        let mut trace = self.traces[i].remove(j);
+                        if trace.inner().is_some() {
+                            trace = trace.into_inner();
+                        }
+                        if let Some(window) = window {
+                            trace = Box::new(SmoothedTracer::new(trace, window));
+                        }
+                        self.traces[i].insert(j, trace);
+                        return;
+                    }
+
+                    if let Some(config) = derived::show_derive(ui, i, &self.traces[i]) {
+                        derive = Some((i, config));
+                    }
+
+                    let mut remove = None;
+                    for (j, trace) in self.traces[i].iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            if trace.is_stale() {
+                                ui.label(RichText::new(format!("⚠ {}", trace.name())).weak())
+                                    .on_hover_text("The traced path or key no longer exists");
+                                if ui.button("Remove").clicked() {
+                                    remove = Some(j);
+                                }
+                            } else if let Some(reason) = trace.tripped() {
+                                ui.label(
+                                    RichText::new(format!("⚠ {}", trace.name()))
+                                        .color(Color32::RED),
+                                )
+                                .on_hover_text(reason);
+                            } else {
+                                ui.label(trace.name());
+                            }
+                            if let Some(coalesce) = trace.coalesce() {
+                                aggregation::show_coalesce(ui, ("coalesce", i, j), coalesce);
+                            }
+                            if let Some(aggregation) = trace.aggregation() {
+                                aggregation::show_aggregation(
+                                    ui,
+                                    ("aggregation", i, j),
+                                    aggregation,
+                                );
+                            }
+                            if let Some(guard) = trace.guard() {
+                                guard::show_guard(ui, guard);
+                            }
+                            if let Some(max_points) = trace.max_points() {
+                                show_max_points(ui, max_points);
+                            }
+                            if ui
+                                .small_button("Clear")
+                                .on_hover_text("Drop all recorded points")
+                                .clicked()
+                            {
+                                trace.clear();
+                            }
                         });
+                    }
+                    if let Some(j) = remove {
+                        self.traces[i].remove(j);
+                        return;
+                    }
 
+                    let plot = &self.traces[i];
                     for (j, trace) in plot.into_iter().enumerate() {
                         if i > 0 && ui.button(format!("^ {}", trace.name())).clicked() {
                             let value = self.traces[i].remove(j);
@@ -45,46 +466,561 @@ impl Application {
                 }
             })
         });
+
+        self.plot_cursor = hovered;
+        if let Some((group, config)) = derive {
+            let tracer = self.tracer_from_config(config);
+            self.traces[group].push(tracer);
+        }
+
+        if let Some(group) = export {
+            self.csv_group = group;
+            self.csv_dialog = FileDialog::new()
+                .initial_directory(self.dir.clone())
+                .default_file_name(&format!("plot-{group}.csv"));
+            self.csv_dialog.save_file();
+        }
+        if let Some(group) = image {
+            self.plot_image
+                .open(&self.dir, group, SimTime::now().as_secs_f64());
+        }
+        self.plot_image.update(ctx);
+        self.csv_dialog.update(ctx);
+        if let Some(path) = self.csv_dialog.take_picked()
+            && let Err(e) = self.export_group(self.csv_group, &path)
+        {
+            ::tracing::error!("failed to export plot to {}: {e}", path.display());
+        }
     }
 }
 
+impl Application {
+    /// Writes the traces of one plot group into a CSV file at `path`.
+    pub fn export_group(&self, group: usize, path: &Path) -> io::Result<()> {
+        let Some(traces) = self.traces.get(group) else {
+            return Ok(());
+        };
+        let traces = traces
+            .iter()
+            .filter(|t| t.style() == TraceStyle::Line)
+            .map(|t| &**t)
+            .collect::<Vec<_>>();
+        if traces.is_empty() {
+            return Ok(());
+        }
+        let f = File::create(path)?;
+        write_group_csv(BufWriter::new(f), &traces)
+    }
+
+    pub fn trace_snapshots(&self) -> Vec<TraceSnapshot> {
+        self.traces
+            .iter()
+            .enumerate()
+            .flat_map(|(group, plot)| {
+                plot.iter().map(move |trace| TraceSnapshot {
+                    group,
+                    config: trace.config(),
+                    points: trace.raw_points().iter().map(|p| [p.x, p.y]).collect(),
+                })
+            })
+            .collect()
+    }
+
+    /// Writes all traces of all plot groups into `traces.csv` in the output
+    /// directory.
+    pub fn export_traces(&self) -> io::Result<()> {
+        let f = File::create(self.dir.join("traces.csv"))?;
+        write_tidy_csv(
+            BufWriter::new(f),
+            self.traces
+                .iter()
+                .flatten()
+                .filter(|t| t.style() == TraceStyle::Line),
+        )
+    }
+
+    /// Builds the tracer described by `config` and observes its paths.
+    pub fn tracer_from_config(&mut self, mut config: TraceConfig) -> Box<dyn Tracer> {
+        if let Some(window) = config.smooth.take() {
+            let inner = self.tracer_from_config(config);
+            return Box::new(SmoothedTracer::new(inner, window));
+        }
+        if let Some(combine) = config.combine {
+            self.observe_matching(&config.path);
+            let mut tracer = AggregateTracer::new(config.path, config.key, combine);
+            if let Some(max_points) = tracer.max_points() {
+                *max_points = config.max_points;
+            }
+            return Box::new(tracer);
+        }
+
+        let path = ObjectPath::from(config.path);
+        self.observe_path(&path);
+        if let Some(derive) = config.derive {
+            let rhs = ObjectPath::from(derive.path);
+            self.observe_path(&rhs);
+            let mut tracer = DerivedTracer::new((path, config.key), derive.op, (rhs, derive.key));
+            if let Some(max_points) = tracer.max_points() {
+                *max_points = config.max_points;
+            }
+            return Box::new(tracer);
+        }
+        match config.vs {
+            Some(vs) => {
+                let x = ObjectPath::from(vs.path);
+                self.observe_path(&x);
+                Box::new(XYTracer::new((x, vs.key), (path, config.key)))
+            }
+            None if config.sequence => Box::new(SequenceTracer::new(path, config.key)),
+            None => {
+                let mut tracer = TreeTracer::new(path, config.key);
+                tracer.max_points = config.max_points;
+                Box::new(tracer)
+            }
+        }
+    }
+
+    /// Starts observing all modules matching `pattern`.
+    fn observe_matching(&mut self, pattern: &str) {
+        let paths = self
+            .rt
+            .sim()
+            .nodes()
+            .into_iter()
+            .filter(|path| matches_pattern(pattern, path.as_str()))
+            .collect::<Vec<_>>();
+        for path in paths {
+            self.observe_path(&path);
+        }
+    }
+
+    /// Rebuilds persisted traces, including their historical points.
+    pub fn restore_traces(&mut self, snapshots: Vec<TraceSnapshot>) {
+        for snapshot in snapshots {
+            // The path of a combined trace is a pattern, not a module
+            let path = ObjectPath::from(snapshot.config.path.as_str());
+            if snapshot.config.combine.is_none() {
+                self.observe_path(&path);
+                if !self.observe.contains_key(&path) {
+                    continue;
+                }
+            }
+
+            let mut tracer = self.tracer_from_config(snapshot.config);
+            tracer.restore_points(
+                snapshot
+                    .points
+                    .into_iter()
+                    .map(|[x, y]| PlotPoint { x, y })
+                    .collect(),
+            );
+
+            while self.traces.len() <= snapshot.group {
+                self.traces.push(Vec::new());
+            }
+            self.traces[snapshot.group].push(tracer);
+        }
+    }
+}
+
+/// A trace and its recorded points, as persisted across sessions.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TraceSnapshot {
+    pub group: usize,
+    pub config: TraceConfig,
+    pub points: Vec<[f64; 2]>,
+}
+
+/// A checkbox to cap the recorded points, and the cap if set.
+fn show_max_points(ui: &mut egui::Ui, max_points: &mut Option<usize>) {
+    let mut capped = max_points.is_some();
+    if ui
+        .checkbox(&mut capped, "Cap")
+        .on_hover_text("Keep only the most recent points")
+        .changed()
+    {
+        *max_points = capped.then_some(DEFAULT_MAX_POINTS);
+    }
+    if let Some(max) = max_points {
+        ui.add(
+            DragValue::new(max)
+                .range(2..=10_000_000)
+                .speed(100.0)
+                .suffix(" points"),
+        );
+    }
+}
+
+/// The range of all recorded values of a plot group, the initial fixed range.
+fn y_extent(plot: &[Box<dyn Tracer>]) -> (f64, f64) {
+    let (min, max) = plot
+        .iter()
+        .flat_map(|t| t.raw_points())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), p| {
+            (min.min(p.y), max.max(p.y))
+        });
+    match min <= max {
+        true => (min, max),
+        false => (0.0, 1.0),
+    }
+}
+
+/// The y bounds of a fixed range in plot coordinates, if the range is valid
+/// for the scale.
+fn y_bounds((min, max): (f64, f64), log: bool) -> Option<(f64, f64)> {
+    let (min, max) = match log {
+        true if min > 0.0 => (min.log10(), max.log10()),
+        true => return None,
+        false => (min, max),
+    };
+    (min < max).then_some((min, max))
+}
+
+/// The pointer distance in pixels within which a log mark shows its message.
+const LOG_HOVER_DISTANCE: f32 = 6.0;
+
+/// Where `module` logged, on the line through `points`, and the index of
+/// each log in its stream. Logs outside the recorded range are not marked.
+///
+/// Messages are only formatted on hover, see [`log_message`].
+fn log_points(
+    logs: &GuiTracingObserver,
+    module: &ObjectPath,
+    points: &[PlotPoint],
+    log: bool,
+) -> Vec<(PlotPoint, usize)> {
+    let streams = logs.streams.lock().expect("failed to lock");
+    let Some(stream) = streams.get(module) else {
+        return Vec::new();
+    };
+    stream
+        .output()
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, event)| {
+            let x = event.time.as_secs_f64();
+            let y = value_at(points, x)?;
+            let y = match log {
+                true if y > 0.0 => y.log10(),
+                true => return None,
+                false => y,
+            };
+            Some((PlotPoint { x, y }, idx))
+        })
+        .collect()
+}
+
+/// The level and fields of the log at `idx` in the stream of `module`.
+fn log_message(logs: &GuiTracingObserver, module: &ObjectPath, idx: usize) -> Option<String> {
+    let streams = logs.streams.lock().expect("failed to lock");
+    let event = streams.get(module)?.output().get(idx)?;
+    Some(format!("{} {}", event.metadata.level(), event.fields))
+}
+
+/// Maps all points to `log10(y)` if `log` is set, dropping those where the
+/// log is undefined.
+fn scale(points: PlotPoints<'_>, log: bool) -> PlotPoints<'_> {
+    if !log {
+        return points;
+    }
+    PlotPoints::Owned(
+        points
+            .points()
+            .iter()
+            .filter(|p| p.y > 0.0)
+            .map(|p| PlotPoint {
+                x: p.x,
+                y: p.y.log10(),
+            })
+            .collect(),
+    )
+}
+
+/// The value of `points` at `x`, linearly interpolated between the
+/// neighbouring points. Steps, i.e. two points at the same x, are kept.
+fn value_at(points: &[PlotPoint], x: f64) -> Option<f64> {
+    let i = points.partition_point(|p| p.x <= x);
+    let prev = points.get(i.checked_sub(1)?)?;
+    let Some(next) = points.get(i) else {
+        return (prev.x == x).then_some(prev.y);
+    };
+    let t = (x - prev.x) / (next.x - prev.x);
+    Some(prev.y + t * (next.y - prev.y))
+}
+
+/// Formats integer series without a decimal point.
+fn format_y(y: f64, integer: bool) -> String {
+    match integer {
+        true => format!("{}", y.round() as i64),
+        false => format!("{y:.3}"),
+    }
+}
+
+const SI_PREFIXES: [(f64, &str); 8] = [
+    (1e12, "T"),
+    (1e9, "G"),
+    (1e6, "M"),
+    (1e3, "k"),
+    (1.0, ""),
+    (1e-3, "m"),
+    (1e-6, "µ"),
+    (1e-9, "n"),
+];
+
+/// Formats `value` with the largest SI prefix that keeps it at or above 1.
+fn format_si(value: f64, unit: &str) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return format!("{value} {unit}");
+    }
+    let (scale, prefix) = SI_PREFIXES
+        .iter()
+        .find(|(scale, _)| value.abs() >= *scale)
+        .unwrap_or(&SI_PREFIXES[SI_PREFIXES.len() - 1]);
+    format!("{} {prefix}{unit}", (value / scale * 100.0).round() / 100.0)
+}
+
+/// The unit of a prop, annotated by a `@unit=..` suffix of its key, e.g.
+/// `bytes_sent@unit=bytes`.
+fn unit_of(key: &str) -> Option<String> {
+    let (_, unit) = key.rsplit_once("@unit=")?;
+    Some(unit.to_string()).filter(|unit| !unit.is_empty())
+}
+
+/// Writes one `time,series,value` row per recorded sample.
+///
+/// Each trace keeps its own time grid, only the synthetic step points are
+/// skipped, since tracers only record changed values.
+fn write_tidy_csv<'a>(
+    mut w: impl Write,
+    traces: impl IntoIterator<Item = &'a Box<dyn Tracer>>,
+) -> io::Result<()> {
+    writeln!(w, "time,series,value")?;
+    for trace in traces {
+        let series = csv_field(trace.name());
+
+        let mut last = None;
+        for p in trace.raw_points() {
+            if last != Some(p.y) {
+                writeln!(w, "{},{},{}", p.x, series, p.y)?;
+            }
+            last = Some(p.y);
+        }
+    }
+    w.flush()
+}
+
+/// Writes a `time` column and one column per trace.
+///
+/// The rows are the union of all sample times, where each trace is forward
+/// filled with its latest value at or before that time.
+fn write_group_csv(mut w: impl Write, traces: &[&dyn Tracer]) -> io::Result<()> {
+    let names = traces
+        .iter()
+        .map(|t| csv_field(t.name()))
+        .collect::<Vec<_>>();
+    writeln!(w, "time,{}", names.join(","))?;
+
+    let mut times = traces
+        .iter()
+        .flat_map(|t| t.raw_points().iter().map(|p| p.x))
+        .collect::<Vec<_>>();
+    times.sort_by(f64::total_cmp);
+    times.dedup();
+
+    let mut cursors = vec![0; traces.len()];
+    for x in times {
+        write!(w, "{x}")?;
+        for (trace, cursor) in traces.iter().zip(&mut cursors) {
+            let points = trace.raw_points();
+            while points.get(*cursor).is_some_and(|p| p.x <= x) {
+                *cursor += 1;
+            }
+            match cursor.checked_sub(1) {
+                Some(i) => write!(w, ",{}", points[i].y)?,
+                None => write!(w, ",")?,
+            }
+        }
+        writeln!(w)?;
+    }
+    w.flush()
+}
+
+fn csv_field(name: String) -> String {
+    if name.contains([',', '"']) {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    } else {
+        name
+    }
+}
+
+/// The cap suggested once a trace is capped.
+const DEFAULT_MAX_POINTS: usize = 10_000;
+
+/// Distinct colors, assigned to traces in order of creation.
+pub const TRACE_COLORS: [Color32; 8] = [
+    Color32::from_rgb(31, 119, 180),
+    Color32::from_rgb(255, 127, 14),
+    Color32::from_rgb(44, 160, 44),
+    Color32::from_rgb(214, 39, 40),
+    Color32::from_rgb(148, 103, 189),
+    Color32::from_rgb(140, 86, 75),
+    Color32::from_rgb(227, 119, 194),
+    Color32::from_rgb(23, 190, 207),
+];
+
+static NEXT_COLOR: AtomicUsize = AtomicUsize::new(0);
+
+fn next_color() -> Color32 {
+    TRACE_COLORS[NEXT_COLOR.fetch_add(1, Ordering::Relaxed) % TRACE_COLORS.len()]
+}
+
+/// Plots over the sim time share their x axis and cursor.
+const TIME_AXIS_GROUP: &str = "time-axis";
+
+/// A labeled sim time, drawn as a vertical line on all plots, e.g. where
+/// a breakpoint fired.
+#[derive(Debug, Clone)]
+pub struct Marker {
+    pub time: SimTime,
+    pub label: String,
+}
+
+/// How a trace is drawn. Only lines have the sim time on the x axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceStyle {
+    Line,
+    Scatter,
+    Bars,
+}
+
 pub trait Tracer {
     fn name(&self) -> String;
+    /// The line color, fixed for the lifetime of the trace.
+    fn color(&self) -> Color32;
     fn needs_path(&self, path: &ObjectPath) -> bool;
-    fn update(&mut self, values: &FxHashMap<ObjectPath, Value>);
+    /// Whether the traced path or key could not be resolved on the last update.
+    fn is_stale(&self) -> bool;
+    /// Records the current value, breaking if it newly trips the guard.
+    fn update(&mut self, values: &FxHashMap<ObjectPath, Value>) -> ControlFlow<()>;
     fn points(&self) -> PlotPoints<'_>;
+    /// The recorded samples, before any aggregation.
+    fn raw_points(&self) -> &[PlotPoint];
+    /// The configuration to rebuild this tracer from.
+    fn config(&self) -> TraceConfig;
+    /// The shaded band drawn around `points`, if any.
+    fn band(&self) -> Option<PlotPoints<'_>> {
+        None
+    }
+    /// The unit of the y values, if the prop key annotates one.
+    fn unit(&self) -> Option<&str> {
+        None
+    }
+    /// The module whose logs are marked on the line, if only one is traced.
+    fn module(&self) -> Option<&ObjectPath> {
+        None
+    }
+    /// Whether all recorded values were integers.
+    fn is_integer(&self) -> bool {
+        false
+    }
+    /// How `points` are drawn.
+    fn style(&self) -> TraceStyle {
+        TraceStyle::Line
+    }
+    /// The aggregation of the points, if the trace supports one.
+    fn aggregation(&mut self) -> Option<&mut Option<Aggregation>> {
+        None
+    }
+    /// How updates at the same sim time are merged, if the trace supports it.
+    fn coalesce(&mut self) -> Option<&mut Option<Coalesce>> {
+        None
+    }
+    /// The guard on the recorded values, if the trace supports one.
+    fn guard(&mut self) -> Option<&mut Option<Guard>> {
+        None
+    }
+    /// Why the guard was tripped by the latest value, if it was.
+    fn tripped(&self) -> Option<&str>;
+    /// Drops all points recorded after `time`.
+    fn truncate(&mut self, time: SimTime);
+    /// Drops all recorded points, the trace continues from the next update.
+    fn clear(&mut self);
+    /// Replaces the recorded points by persisted ones. Only traces over the
+    /// sim time keep them, since only those can be truncated on replay.
+    fn restore_points(&mut self, _points: Vec<PlotPoint>) {}
+    /// The cap on the number of recorded points, if the trace supports one.
+    fn max_points(&mut self) -> Option<&mut Option<usize>> {
+        None
+    }
+    /// The wrapped trace, drawn alongside this one.
+    fn inner(&self) -> Option<&dyn Tracer> {
+        None
+    }
+    /// Unwraps the inner trace, or returns this trace if it wraps none.
+    fn into_inner(self: Box<Self>) -> Box<dyn Tracer>;
 }
 
 pub struct TreeTracer {
     path: ObjectPath,
     key: String,
     values: Vec<PlotPoint>,
+    aggregation: Option<Aggregation>,
+    coalesce: Option<Coalesce>,
+    guard: Option<Guard>,
+    tripped: Option<String>,
+    stale: bool,
+    color: Color32,
+    unit: Option<String>,
+    // Cleared once a float is recorded
+    integer: bool,
+    max_points: Option<usize>,
 }
 
 impl TreeTracer {
     pub fn new(module: ObjectPath, key: String) -> Self {
         Self {
             path: module,
+            unit: unit_of(&key),
             key,
             values: Vec::new(),
+            integer: true,
+            aggregation: None,
+            coalesce: Some(Coalesce::Last),
+            guard: None,
+            tripped: None,
+            stale: false,
+            color: next_color(),
+            max_points: None,
         }
     }
-}
-
-impl Tracer for TreeTracer {
-    fn name(&self) -> String {
-        format!("{} {}", self.path, self.key)
-    }
 
-    fn needs_path(&self, path: &ObjectPath) -> bool {
-        self.path == *path
-    }
+    /// Records the current value of the traced key, `resolved` tells
+    /// whether the traced module is observed at all.
+    fn record(&mut self, resolved: bool, value: Option<Value>) -> ControlFlow<()> {
+        // A key that never had a value yet is not considered stale
+        self.stale = !resolved || (value.is_none() && !self.values.is_empty());
 
-    fn update(&mut self, values: &FxHashMap<ObjectPath, Value>) {
-        let map = values.get(&self.path).expect("message not observed");
+        let y = value.as_ref().and_then(Value::as_f64);
+        if let Some(Value::Number(n)) = &value
+            && n.is_f64()
+        {
+            self.integer = false;
+        }
+        let tripped = self.guard.zip(y).and_then(|(guard, y)| guard.check(y));
+        // Only break on the transition, so that the sim can be resumed
+        let flow = match (&self.tripped, &tripped) {
+            (None, Some(_)) => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        };
+        self.tripped = tripped;
 
-        if let Some(y) = access(map, &self.key).and_then(|v| v.as_f64()) {
+        if let Some(y) = y {
             let x = SimTime::now().as_secs_f64();
+            // Restored points from a previous session are replaced, once the
+            // sim re-runs past them.
+            if self.values.last().is_some_and(|p| p.x > x) {
+                self.values.retain(|p| p.x <= x);
+            }
             if let Some(last_y) = self.values.last().map(|p| p.y) {
                 if last_y != y {
                     self.values.push(PlotPoint { x, y: last_y }); // make a stepper
@@ -93,11 +1029,125 @@ impl Tracer for TreeTracer {
             } else {
                 self.values.push(PlotPoint { x, y });
             }
+            if let Some(max) = self.max_points {
+                let excess = self.values.len().saturating_sub(max);
+                self.values.drain(..excess);
+            }
         }
+        flow
+    }
+}
+
+impl Tracer for TreeTracer {
+    fn name(&self) -> String {
+        format!("{} {}", self.path, self.key)
+    }
+
+    fn color(&self) -> Color32 {
+        self.color
+    }
+
+    fn needs_path(&self, path: &ObjectPath) -> bool {
+        self.path == *path
+    }
+
+    fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    fn update(&mut self, values: &FxHashMap<ObjectPath, Value>) -> ControlFlow<()> {
+        let map = values.get(&self.path);
+        let value = map.and_then(|map| access(map, &self.key));
+        self.record(map.is_some(), value)
     }
 
     fn points(&self) -> PlotPoints<'_> {
-        PlotPoints::Borrowed(&self.values)
+        let values = match self.coalesce {
+            Some(coalesce) => Cow::Owned(coalesce.apply(&self.values)),
+            None => Cow::Borrowed(&self.values[..]),
+        };
+
+        match (self.aggregation, values) {
+            (Some(aggregation), values) => PlotPoints::Owned(aggregation.apply(&values)),
+            (None, Cow::Owned(values)) => PlotPoints::Owned(values),
+            (None, Cow::Borrowed(values)) => PlotPoints::Borrowed(values),
+        }
+    }
+
+    fn raw_points(&self) -> &[PlotPoint] {
+        &self.values
+    }
+
+    fn config(&self) -> TraceConfig {
+        TraceConfig {
+            path: self.path.to_string(),
+            key: self.key.clone(),
+            vs: None,
+            sequence: false,
+            max_points: self.max_points,
+            combine: None,
+            derive: None,
+            smooth: None,
+        }
+    }
+
+    fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+
+    fn module(&self) -> Option<&ObjectPath> {
+        Some(&self.path)
+    }
+
+    fn is_integer(&self) -> bool {
+        // An average of integers is not one
+        self.integer
+            && self.aggregation.is_none()
+            && self.coalesce != Some(Coalesce::Mean)
+            && !self.values.is_empty()
+    }
+
+    fn band(&self) -> Option<PlotPoints<'_>> {
+        self.aggregation?.band(&self.values).map(PlotPoints::Owned)
+    }
+
+    fn aggregation(&mut self) -> Option<&mut Option<Aggregation>> {
+        Some(&mut self.aggregation)
+    }
+
+    fn coalesce(&mut self) -> Option<&mut Option<Coalesce>> {
+        Some(&mut self.coalesce)
+    }
+
+    fn guard(&mut self) -> Option<&mut Option<Guard>> {
+        Some(&mut self.guard)
+    }
+
+    fn tripped(&self) -> Option<&str> {
+        self.tripped.as_deref()
+    }
+
+    fn truncate(&mut self, time: SimTime) {
+        let x = time.as_secs_f64();
+        self.values.retain(|p| p.x <= x);
+        self.tripped = None;
+    }
+
+    fn clear(&mut self) {
+        self.values.clear();
+        self.tripped = None;
+    }
+
+    fn restore_points(&mut self, points: Vec<PlotPoint>) {
+        self.values = points;
+    }
+
+    fn max_points(&mut self) -> Option<&mut Option<usize>> {
+        Some(&mut self.max_points)
+    }
+
+    fn into_inner(self: Box<Self>) -> Box<dyn Tracer> {
+        self
     }
 }
 
@@ -134,12 +1184,120 @@ pub fn access(value: &Value, key: &str) -> Option<Value> {
     }
 }
 
+/// The observed values of a single module with a single key, parsed from
+/// YAML, e.g. `!Sending 1`.
+#[cfg(test)]
+pub fn observers(
+    path: &ObjectPath,
+    key: &str,
+    value: impl std::fmt::Display,
+) -> FxHashMap<ObjectPath, Value> {
+    let value = serde_norway::from_str(&format!("{key}: {value}")).unwrap();
+    FxHashMap::from_iter([(path.clone(), value)])
+}
+
 #[cfg(test)]
 mod tests {
     use serde_norway::{Mapping, Sequence};
 
     use super::*;
 
+    #[test]
+    fn interpolates_at_cursor() {
+        let points =
+            [(0.0, 1.0), (1.0, 1.0), (1.0, 2.0), (3.0, 4.0)].map(|(x, y)| PlotPoint { x, y });
+        assert_eq!(value_at(&points, -1.0), None);
+        assert_eq!(value_at(&points, 0.5), Some(1.0));
+        assert_eq!(value_at(&points, 1.0), Some(2.0));
+        assert_eq!(value_at(&points, 2.0), Some(3.0));
+        assert_eq!(value_at(&points, 3.0), Some(4.0));
+        assert_eq!(value_at(&points, 4.0), None);
+    }
+
+    #[test]
+    fn fixed_y_bounds() {
+        assert_eq!(y_bounds((0.0, 10.0), false), Some((0.0, 10.0)));
+        assert_eq!(y_bounds((1.0, 100.0), true), Some((0.0, 2.0)));
+        assert_eq!(y_bounds((0.0, 100.0), true), None);
+        assert_eq!(y_bounds((5.0, 5.0), false), None);
+        assert_eq!(y_extent(&[]), (0.0, 1.0));
+    }
+
+    #[test]
+    fn tidy_csv_skips_step_points() {
+        let mut tracer = TreeTracer::new(ObjectPath::from("ping"), "counter".to_string());
+        tracer.values = [(0.0, 1.0), (1.0, 1.0), (1.0, 2.0), (2.5, 2.0), (2.5, 3.0)]
+            .into_iter()
+            .map(|(x, y)| PlotPoint { x, y })
+            .collect();
+        let traces: Vec<Box<dyn Tracer>> = vec![Box::new(tracer)];
+
+        let mut out = Vec::new();
+        write_tidy_csv(&mut out, &traces).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "time,series,value\n0,ping counter,1\n1,ping counter,2\n2.5,ping counter,3\n"
+        );
+    }
+
+    #[test]
+    fn caps_and_clears_points() {
+        let path = ObjectPath::from("ping");
+        let mut tracer = TreeTracer::new(path.clone(), "counter".to_string());
+        tracer.max_points = Some(3);
+
+        let mut values = FxHashMap::default();
+        for counter in 1..=3 {
+            let module = Mapping::from_iter([(Value::from("counter"), Value::from(counter))]);
+            values.insert(path.clone(), Value::Mapping(module));
+            let _ = tracer.update(&values);
+        }
+        let ys = tracer.raw_points().iter().map(|p| p.y).collect::<Vec<_>>();
+        assert_eq!(ys, [2.0, 2.0, 3.0]);
+
+        tracer.clear();
+        assert!(tracer.raw_points().is_empty());
+        let _ = tracer.update(&values);
+        assert_eq!(tracer.raw_points().len(), 1);
+    }
+
+    #[test]
+    fn group_csv_forward_fills() {
+        let tracer = |key: &str, points: &[(f64, f64)]| -> Box<dyn Tracer> {
+            let mut tracer = TreeTracer::new(ObjectPath::from("ping"), key.to_string());
+            tracer.values = points.iter().map(|&(x, y)| PlotPoint { x, y }).collect();
+            Box::new(tracer)
+        };
+        let traces = vec![
+            tracer("a", &[(0.0, 1.0), (2.0, 1.0), (2.0, 2.0)]),
+            tracer("b", &[(1.0, 5.0)]),
+        ];
+
+        let mut out = Vec::new();
+        write_group_csv(&mut out, &traces.iter().map(|t| &**t).collect::<Vec<_>>()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "time,ping a,ping b\n0,1,\n1,1,5\n2,2,5\n"
+        );
+    }
+
+    #[test]
+    fn si_prefixed_units() {
+        assert_eq!(unit_of("bytes_sent.@unit=bytes").as_deref(), Some("bytes"));
+        assert_eq!(unit_of("counter"), None);
+
+        assert_eq!(format_si(1_500.0, "B"), "1.5 kB");
+        assert_eq!(format_si(0.002, "s"), "2 ms");
+        assert_eq!(format_si(42.0, "B"), "42 B");
+        assert_eq!(format_si(0.0, "B"), "0 B");
+    }
+
+    #[test]
+    fn integers_without_decimal_point() {
+        assert_eq!(format_y(42.0, true), "42");
+        assert_eq!(format_y(42.0, false), "42.000");
+    }
+
     #[test]
     fn access_multi_keys() {
         let value = Value::Mapping(Mapping::from_iter([(
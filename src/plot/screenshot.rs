@@ -0,0 +1,74 @@
+use std::path::{Path, PathBuf};
+
+use egui::{Context, Event, Rect, ViewportCommand};
+use egui_file_dialog::FileDialog;
+
+/// Saves a plot group as a PNG, as currently shown.
+///
+/// The plot is not re-rendered offscreen, instead a screenshot of the
+/// viewport is cropped to the plot, which preserves zoom, pan and scale.
+/// Saving takes three steps: picking a path, requesting the screenshot
+/// once the dialog is closed and writing it once it arrived.
+#[derive(Default)]
+pub struct PlotScreenshot {
+    dialog: FileDialog,
+    group: usize,
+    // The picked path, until the screenshot is requested
+    path: Option<PathBuf>,
+    // The plot region and path, until the screenshot arrived
+    pending: Option<(Rect, PathBuf)>,
+}
+
+impl PlotScreenshot {
+    pub fn open(&mut self, dir: &Path, group: usize, time: f64) {
+        self.group = group;
+        self.dialog = FileDialog::new()
+            .initial_directory(dir.to_path_buf())
+            .default_file_name(&format!("plot-{group}-{time:.3}s.png"));
+        self.dialog.save_file();
+    }
+
+    /// Requests the screenshot, if `group` is awaiting one.
+    pub fn capture(&mut self, ctx: &Context, group: usize, rect: Rect) {
+        if group != self.group {
+            return;
+        }
+        if let Some(path) = self.path.take() {
+            self.pending = Some((rect, path));
+            ctx.send_viewport_cmd(ViewportCommand::Screenshot(Default::default()));
+        }
+    }
+
+    pub fn update(&mut self, ctx: &Context) {
+        self.dialog.update(ctx);
+        if let Some(path) = self.dialog.take_picked() {
+            self.path = Some(path);
+        }
+
+        if self.pending.is_none() {
+            return;
+        }
+        let image = ctx.input(|i| {
+            i.raw.events.iter().find_map(|e| match e {
+                Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        if let Some(image) = image
+            && let Some((rect, path)) = self.pending.take()
+        {
+            let region = image.region(&rect, Some(ctx.pixels_per_point()));
+            let [w, h] = region.size;
+            match image::save_buffer(
+                &path,
+                region.as_raw(),
+                w as u32,
+                h as u32,
+                image::ColorType::Rgba8,
+            ) {
+                Ok(()) => ::tracing::info!("saved plot to {}", path.display()),
+                Err(e) => ::tracing::error!("failed to save plot to {}: {e}", path.display()),
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::ops::ControlFlow;
+
+use des::{net::ObjectPath, time::SimTime};
+use egui::Color32;
+use egui_plot::{PlotPoint, PlotPoints};
+use fxhash::FxHashMap;
+use serde_norway::Value;
+
+use super::{Guard, TraceStyle, Tracer, access, next_color, unit_of};
+use crate::config::TraceConfig;
+
+/// The current elements of a numeric sequence, e.g. a histogram.
+///
+/// Unlike other traces, no history is recorded. Each update replaces the
+/// points with `(index, element)`, drawn as a bar chart.
+pub struct SequenceTracer {
+    path: ObjectPath,
+    key: String,
+    values: Vec<PlotPoint>,
+    guard: Option<Guard>,
+    tripped: Option<String>,
+    stale: bool,
+    color: Color32,
+    unit: Option<String>,
+}
+
+impl SequenceTracer {
+    pub fn new(path: ObjectPath, key: String) -> Self {
+        Self {
+            path,
+            unit: unit_of(&key),
+            key,
+            values: Vec::new(),
+            guard: None,
+            tripped: None,
+            stale: false,
+            color: next_color(),
+        }
+    }
+}
+
+/// The elements of `value` as bars, if it is a sequence of numbers.
+fn elements(value: &Value) -> Option<Vec<PlotPoint>> {
+    let Value::Sequence(seq) = value else {
+        return None;
+    };
+    seq.iter()
+        .enumerate()
+        .map(|(i, v)| {
+            Some(PlotPoint {
+                x: i as f64,
+                y: v.as_f64()?,
+            })
+        })
+        .collect()
+}
+
+impl Tracer for SequenceTracer {
+    fn name(&self) -> String {
+        format!("{} {}", self.path, self.key)
+    }
+
+    fn color(&self) -> Color32 {
+        self.color
+    }
+
+    fn needs_path(&self, path: &ObjectPath) -> bool {
+        self.path == *path
+    }
+
+    fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    fn update(&mut self, values: &FxHashMap<ObjectPath, Value>) -> ControlFlow<()> {
+        let map = values.get(&self.path);
+        let value = map.and_then(|map| access(map, &self.key));
+        self.stale = map.is_none() || (value.is_none() && !self.values.is_empty());
+
+        let Some(points) = value.as_ref().and_then(elements) else {
+            return ControlFlow::Continue(());
+        };
+        // The guard applies to every element
+        let tripped = self
+            .guard
+            .and_then(|guard| points.iter().find_map(|p| guard.check(p.y)));
+        let flow = match (&self.tripped, &tripped) {
+            (None, Some(_)) => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        };
+        self.tripped = tripped;
+        self.values = points;
+        flow
+    }
+
+    fn points(&self) -> PlotPoints<'_> {
+        PlotPoints::Borrowed(&self.values)
+    }
+
+    fn raw_points(&self) -> &[PlotPoint] {
+        &self.values
+    }
+
+    fn config(&self) -> TraceConfig {
+        TraceConfig {
+            path: self.path.to_string(),
+            key: self.key.clone(),
+            vs: None,
+            sequence: true,
+            max_points: None,
+            combine: None,
+            derive: None,
+            smooth: None,
+        }
+    }
+
+    fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+
+    fn style(&self) -> TraceStyle {
+        TraceStyle::Bars
+    }
+
+    fn guard(&mut self) -> Option<&mut Option<Guard>> {
+        Some(&mut self.guard)
+    }
+
+    fn tripped(&self) -> Option<&str> {
+        self.tripped.as_deref()
+    }
+
+    /// The points are not a history, the next update replaces them.
+    fn truncate(&mut self, _time: SimTime) {}
+
+    fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    fn into_inner(self: Box<Self>) -> Box<dyn Tracer> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plot::observers;
+
+    #[test]
+    fn numeric_sequences_only() {
+        let value = serde_norway::from_str::<Value>("[1, 2.5, 0]").unwrap();
+        assert_eq!(
+            elements(&value).unwrap(),
+            [
+                PlotPoint { x: 0.0, y: 1.0 },
+                PlotPoint { x: 1.0, y: 2.5 },
+                PlotPoint { x: 2.0, y: 0.0 }
+            ]
+        );
+
+        let value = serde_norway::from_str::<Value>("[1, a]").unwrap();
+        assert!(elements(&value).is_none());
+    }
+
+    #[test]
+    fn guard_checks_every_element() {
+        let path = ObjectPath::from("ping");
+        let mut tracer = SequenceTracer::new(path.clone(), "hist".to_string());
+        tracer.guard = Some(Guard { bound: Some(10.0) });
+
+        let mut flows = Vec::new();
+        for hist in ["[1, 2]", "[1, 20]", "[1, 30]", "[1, 2]"] {
+            flows.push(tracer.update(&observers(&path, "hist", hist)).is_break());
+        }
+        assert_eq!(flows, [false, true, false, false]);
+        assert!(tracer.tripped().is_none());
+    }
+}
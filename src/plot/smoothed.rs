@@ -0,0 +1,164 @@
+use std::ops::ControlFlow;
+
+use des::{net::ObjectPath, time::SimTime};
+use egui::Color32;
+use egui_plot::{PlotPoint, PlotPoints};
+use fxhash::FxHashMap;
+use serde_norway::Value;
+
+use super::{Aggregation, Coalesce, Guard, TraceStyle, Tracer};
+use crate::config::TraceConfig;
+
+/// A rolling mean over the points of another trace.
+///
+/// The inner trace is drawn alongside, so the smoothed line can be compared
+/// with the raw series.
+pub struct SmoothedTracer {
+    inner: Box<dyn Tracer>,
+    window: usize,
+}
+
+impl SmoothedTracer {
+    pub fn new(inner: Box<dyn Tracer>, window: usize) -> Self {
+        Self {
+            inner,
+            window: window.max(1),
+        }
+    }
+}
+
+/// The mean of each point and up to `window - 1` preceding points.
+fn rolling_mean(points: &[PlotPoint], window: usize) -> Vec<PlotPoint> {
+    let mut sum = 0.0;
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            sum += p.y;
+            if i >= window {
+                sum -= points[i - window].y;
+            }
+            PlotPoint {
+                x: p.x,
+                y: sum / (i + 1).min(window) as f64,
+            }
+        })
+        .collect()
+}
+
+impl Tracer for SmoothedTracer {
+    fn name(&self) -> String {
+        format!("{} (avg {})", self.inner.name(), self.window)
+    }
+
+    fn color(&self) -> Color32 {
+        self.inner.color()
+    }
+
+    fn needs_path(&self, path: &ObjectPath) -> bool {
+        self.inner.needs_path(path)
+    }
+
+    fn is_stale(&self) -> bool {
+        self.inner.is_stale()
+    }
+
+    fn update(&mut self, values: &FxHashMap<ObjectPath, Value>) -> ControlFlow<()> {
+        self.inner.update(values)
+    }
+
+    fn points(&self) -> PlotPoints<'_> {
+        PlotPoints::Owned(rolling_mean(self.inner.points().points(), self.window))
+    }
+
+    fn raw_points(&self) -> &[PlotPoint] {
+        self.inner.raw_points()
+    }
+
+    fn config(&self) -> TraceConfig {
+        TraceConfig {
+            smooth: Some(self.window),
+            ..self.inner.config()
+        }
+    }
+
+    fn unit(&self) -> Option<&str> {
+        self.inner.unit()
+    }
+
+    fn module(&self) -> Option<&ObjectPath> {
+        self.inner.module()
+    }
+
+    fn style(&self) -> TraceStyle {
+        self.inner.style()
+    }
+
+    fn aggregation(&mut self) -> Option<&mut Option<Aggregation>> {
+        self.inner.aggregation()
+    }
+
+    fn coalesce(&mut self) -> Option<&mut Option<Coalesce>> {
+        self.inner.coalesce()
+    }
+
+    fn guard(&mut self) -> Option<&mut Option<Guard>> {
+        self.inner.guard()
+    }
+
+    fn tripped(&self) -> Option<&str> {
+        self.inner.tripped()
+    }
+
+    fn truncate(&mut self, time: SimTime) {
+        self.inner.truncate(time);
+    }
+
+    fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    fn restore_points(&mut self, points: Vec<PlotPoint>) {
+        self.inner.restore_points(points);
+    }
+
+    fn max_points(&mut self) -> Option<&mut Option<usize>> {
+        self.inner.max_points()
+    }
+
+    fn inner(&self) -> Option<&dyn Tracer> {
+        Some(&*self.inner)
+    }
+
+    fn into_inner(self: Box<Self>) -> Box<dyn Tracer> {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plot::TreeTracer;
+
+    #[test]
+    fn rolling_mean_over_window() {
+        let points = [1.0, 3.0, 5.0, 7.0]
+            .into_iter()
+            .enumerate()
+            .map(|(x, y)| PlotPoint { x: x as f64, y })
+            .collect::<Vec<_>>();
+        let ys = rolling_mean(&points, 2)
+            .into_iter()
+            .map(|p| p.y)
+            .collect::<Vec<_>>();
+        assert_eq!(ys, [1.0, 2.0, 4.0, 6.0]);
+    }
+
+    #[test]
+    fn config_keeps_window() {
+        let inner = TreeTracer::new(ObjectPath::from("ping"), "rtt".to_string());
+        let config = SmoothedTracer::new(Box::new(inner), 3).config();
+        assert_eq!(config.smooth, Some(3));
+        assert_eq!(config.key, "rtt");
+    }
+}
@@ -0,0 +1,186 @@
+use std::ops::ControlFlow;
+
+use des::{net::ObjectPath, time::SimTime};
+use egui::Color32;
+use egui_plot::{PlotPoint, PlotPoints};
+use fxhash::FxHashMap;
+use serde_norway::Value;
+
+use super::{Guard, TraceStyle, Tracer, access, next_color, unit_of};
+use crate::{TreeTraceReq, config::TraceConfig};
+
+/// One property plotted against another, e.g. queue length vs. latency.
+///
+/// Each update samples both keys and records a point, if both resolve to
+/// numbers. The points are drawn as a scatter plot, not as a line over time.
+pub struct XYTracer {
+    x: TreeTraceReq,
+    y: TreeTraceReq,
+    values: Vec<PlotPoint>,
+    // The sim time of each point in `values`
+    times: Vec<f64>,
+    guard: Option<Guard>,
+    tripped: Option<String>,
+    stale: bool,
+    color: Color32,
+    // The unit of the y values
+    unit: Option<String>,
+}
+
+impl XYTracer {
+    pub fn new(x: TreeTraceReq, y: TreeTraceReq) -> Self {
+        Self {
+            unit: unit_of(&y.1),
+            x,
+            y,
+            values: Vec::new(),
+            times: Vec::new(),
+            guard: None,
+            tripped: None,
+            stale: false,
+            color: next_color(),
+        }
+    }
+
+    fn sample(values: &FxHashMap<ObjectPath, Value>, (path, key): &TreeTraceReq) -> Option<f64> {
+        access(values.get(path)?, key)?.as_f64()
+    }
+}
+
+impl Tracer for XYTracer {
+    fn name(&self) -> String {
+        format!("{} {} vs {} {}", self.y.0, self.y.1, self.x.0, self.x.1)
+    }
+
+    fn color(&self) -> Color32 {
+        self.color
+    }
+
+    fn needs_path(&self, path: &ObjectPath) -> bool {
+        self.x.0 == *path || self.y.0 == *path
+    }
+
+    fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    fn update(&mut self, values: &FxHashMap<ObjectPath, Value>) -> ControlFlow<()> {
+        let (x, y) = (Self::sample(values, &self.x), Self::sample(values, &self.y));
+        self.stale = !values.contains_key(&self.x.0)
+            || !values.contains_key(&self.y.0)
+            || ((x.is_none() || y.is_none()) && !self.values.is_empty());
+
+        let tripped = self.guard.zip(y).and_then(|(guard, y)| guard.check(y));
+        let flow = match (&self.tripped, &tripped) {
+            (None, Some(_)) => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        };
+        self.tripped = tripped;
+
+        if let (Some(x), Some(y)) = (x, y) {
+            let point = PlotPoint { x, y };
+            if self.values.last() != Some(&point) {
+                self.values.push(point);
+                self.times.push(SimTime::now().as_secs_f64());
+            }
+        }
+        flow
+    }
+
+    fn points(&self) -> PlotPoints<'_> {
+        PlotPoints::Borrowed(&self.values)
+    }
+
+    fn raw_points(&self) -> &[PlotPoint] {
+        &self.values
+    }
+
+    fn config(&self) -> TraceConfig {
+        TraceConfig {
+            path: self.y.0.to_string(),
+            key: self.y.1.clone(),
+            vs: Some(Box::new(TraceConfig {
+                path: self.x.0.to_string(),
+                key: self.x.1.clone(),
+                vs: None,
+                sequence: false,
+                max_points: None,
+                combine: None,
+                derive: None,
+                smooth: None,
+            })),
+            sequence: false,
+            max_points: None,
+            combine: None,
+            derive: None,
+            smooth: None,
+        }
+    }
+
+    fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+
+    fn style(&self) -> TraceStyle {
+        TraceStyle::Scatter
+    }
+
+    fn guard(&mut self) -> Option<&mut Option<Guard>> {
+        Some(&mut self.guard)
+    }
+
+    fn tripped(&self) -> Option<&str> {
+        self.tripped.as_deref()
+    }
+
+    fn truncate(&mut self, time: SimTime) {
+        let t = time.as_secs_f64();
+        let n = self.times.partition_point(|&x| x <= t);
+        self.values.truncate(n);
+        self.times.truncate(n);
+        self.tripped = None;
+    }
+
+    fn clear(&mut self) {
+        self.values.clear();
+        self.times.clear();
+        self.tripped = None;
+    }
+
+    fn into_inner(self: Box<Self>) -> Box<dyn Tracer> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_norway::Mapping;
+
+    use super::*;
+
+    #[test]
+    fn samples_both_keys() {
+        let module = |a: f64, b: f64| {
+            Value::Mapping(Mapping::from_iter([
+                (Value::String("a".to_string()), Value::from(a)),
+                (Value::String("b".to_string()), Value::from(b)),
+            ]))
+        };
+        let path = ObjectPath::from("ping");
+        let mut tracer = XYTracer::new(
+            (path.clone(), "a".to_string()),
+            (path.clone(), "b".to_string()),
+        );
+
+        let mut values = FxHashMap::default();
+        for (a, b) in [(1.0, 2.0), (1.0, 2.0), (3.0, 2.0)] {
+            values.insert(path.clone(), module(a, b));
+            let _ = tracer.update(&values);
+        }
+        assert_eq!(
+            tracer.raw_points(),
+            [PlotPoint { x: 1.0, y: 2.0 }, PlotPoint { x: 3.0, y: 2.0 }]
+        );
+        assert!(!tracer.is_stale());
+    }
+}
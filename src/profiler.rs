@@ -0,0 +1,168 @@
+use std::time::Duration;
+
+use des::{net::ObjectPath, time::SimTime};
+use egui::{Context, Grid, ScrollArea, Window};
+use egui_plot::{Bar, BarChart, Plot};
+use fxhash::FxHashMap;
+
+use crate::Application;
+
+const TOP_N: usize = 25;
+
+/// A single dispatched event and its wall time.
+///
+/// The handling module is taken from the module watermark of the tracing
+/// capture, thus events whose handler did not log are unattributed.
+#[derive(Debug, Clone)]
+pub struct ProfiledEvent {
+    pub index: usize,
+    pub time: SimTime,
+    pub module: Option<ObjectPath>,
+    pub duration: Duration,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ModuleProfile {
+    pub count: usize,
+    pub total: Duration,
+}
+
+#[derive(Debug, Default)]
+pub struct Profiler {
+    pub enabled: bool,
+    slowest: Vec<ProfiledEvent>,
+    per_module: FxHashMap<Option<ObjectPath>, ModuleProfile>,
+}
+
+impl Profiler {
+    pub fn record(&mut self, event: ProfiledEvent) {
+        let profile = self.per_module.entry(event.module.clone()).or_default();
+        profile.count += 1;
+        profile.total += event.duration;
+
+        if self.slowest.len() < TOP_N
+            || self
+                .slowest
+                .last()
+                .is_some_and(|last| last.duration < event.duration)
+        {
+            let pos = self
+                .slowest
+                .partition_point(|e| e.duration >= event.duration);
+            self.slowest.insert(pos, event);
+            self.slowest.truncate(TOP_N);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.slowest.clear();
+        self.per_module.clear();
+    }
+}
+
+fn module_name(module: &Option<ObjectPath>) -> &str {
+    module.as_ref().map_or("-", |path| path.as_str())
+}
+
+impl Application {
+    pub fn render_profiler(&mut self, ctx: &Context) {
+        let mut open = self.show_profiler;
+        Window::new("Profiler").open(&mut open).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.profiler.enabled, "Measure events");
+                if ui.button("Clear").clicked() {
+                    self.profiler.clear();
+                }
+            });
+            ui.separator();
+
+            ScrollArea::vertical().show(ui, |ui| {
+                ui.heading("Slowest events");
+                Grid::new("profiler-slowest").striped(true).show(ui, |ui| {
+                    ui.strong("#");
+                    ui.strong("time");
+                    ui.strong("module");
+                    ui.strong("duration");
+                    ui.end_row();
+
+                    for event in &self.profiler.slowest {
+                        ui.label(event.index.to_string());
+                        ui.label(format!("{:?}", event.time));
+                        ui.label(module_name(&event.module));
+                        ui.label(format!("{:?}", event.duration));
+                        ui.end_row();
+                    }
+                });
+
+                ui.separator();
+                ui.heading("Per module");
+
+                let mut modules = self.profiler.per_module.iter().collect::<Vec<_>>();
+                modules.sort_by(|l, r| r.1.total.cmp(&l.1.total));
+
+                Grid::new("profiler-modules").striped(true).show(ui, |ui| {
+                    ui.strong("module");
+                    ui.strong("events");
+                    ui.strong("total");
+                    ui.strong("mean");
+                    ui.end_row();
+
+                    for (module, profile) in modules {
+                        ui.label(module_name(module));
+                        ui.label(profile.count.to_string());
+                        ui.label(format!("{:?}", profile.total));
+                        ui.label(format!("{:?}", profile.total / profile.count as u32));
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+        self.show_profiler = open;
+    }
+
+    pub fn render_event_counts(&mut self, ctx: &Context) {
+        let mut counts = self.event_counts.iter().collect::<Vec<_>>();
+        counts.sort_by(|l, r| r.1.cmp(l.1).then_with(|| l.0.as_str().cmp(r.0.as_str())));
+
+        let mut open = self.show_event_counts;
+        let clicked = Window::new("Events per module")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let bars = counts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (path, count))| Bar::new(i as f64, **count as f64).name(path))
+                    .collect();
+
+                Plot::new("event-counts")
+                    .view_aspect(2.0)
+                    .show_x(false)
+                    .x_axis_formatter(|mark, _| {
+                        let i = mark.value.round();
+                        match counts.get(i as usize) {
+                            Some((path, _)) if i == mark.value && i >= 0.0 => path.to_string(),
+                            _ => String::new(),
+                        }
+                    })
+                    .show(ui, |ui| {
+                        ui.bar_chart(BarChart::new(bars));
+
+                        // Bars are placed at integer x, so a click selects the nearest.
+                        ui.response()
+                            .clicked()
+                            .then(|| ui.pointer_coordinate())
+                            .flatten()
+                            .and_then(|p| counts.get(p.x.round().max(0.0) as usize))
+                            .map(|(path, _)| (*path).clone())
+                    })
+                    .inner
+            })
+            .and_then(|resp| resp.inner)
+            .flatten();
+        self.show_event_counts = open;
+
+        if let Some(path) = clicked {
+            self.open_inspector(path);
+        }
+    }
+}
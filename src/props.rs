@@ -0,0 +1,52 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+use des::prelude::*;
+use serde_norway::Value;
+
+/// Overwrites the prop `key` of the module at `path`.
+///
+/// Props are typed, thus the candidate types are probed based on the parsed
+/// value until one matches the existing prop. Only existing props can be set.
+///
+/// Strings are also probed as addresses, which are displayed in their string
+/// form, e.g. `10.0.0.1` or `[::1]:80`.
+pub fn set_prop(sim: &Sim<()>, path: &ObjectPath, key: &str, value: Value) -> Result<(), String> {
+    let module = sim
+        .globals()
+        .get(path)
+        .ok_or_else(|| format!("no module at {path}"))?;
+    if !module.props_keys().iter().any(|k| k == key) {
+        return Err(format!("{path} has no prop {key}"));
+    }
+
+    macro_rules! probe {
+        ($($t:ty),*) => {{
+            $(
+                if let Ok(v) = serde_norway::from_value::<$t>(value.clone())
+                    && let Ok(mut prop) = module.prop::<$t>(key)
+                {
+                    prop.set(v);
+                    return Ok(());
+                }
+            )*
+        }};
+    }
+
+    match &value {
+        Value::Number(n) if n.is_f64() => probe!(f64, f32),
+        Value::Number(_) => probe!(usize, u64, u32, u16, u8, isize, i64, i32, i16, i8, f64, f32),
+        Value::String(_) => probe!(
+            String,
+            Ipv4Addr,
+            Ipv6Addr,
+            IpAddr,
+            SocketAddrV4,
+            SocketAddrV6,
+            SocketAddr
+        ),
+        Value::Bool(_) => probe!(bool),
+        _ => {}
+    }
+
+    Err(format!("prop {key} of {path} does not accept {value:?}"))
+}
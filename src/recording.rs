@@ -0,0 +1,362 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::Path,
+};
+
+use des::net::ObjectPath;
+use egui::{Context, Ui};
+use egui_file_dialog::FileDialog;
+use serde::{Deserialize, Serialize};
+use serde_norway::Value;
+
+use crate::{ActionReq, Application, Combine, RunState, config::GuiConfig};
+
+/// An input to the debugger, recorded to reproduce a session.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum Input {
+    Breakpoint {
+        path: String,
+        key: String,
+        value: Option<Value>,
+    },
+    Trace {
+        path: String,
+        key: String,
+    },
+    TraceVs {
+        x: (String, String),
+        y: (String, String),
+    },
+    TraceSequence {
+        path: String,
+        key: String,
+    },
+    TraceAcross {
+        path: String,
+        key: String,
+        combine: Combine,
+    },
+    Watch {
+        path: String,
+        key: String,
+    },
+    SetProp {
+        path: String,
+        key: String,
+        value: Value,
+    },
+    OpenInspector {
+        path: String,
+    },
+    /// A press of a run control, e.g. "Step".
+    Run(RunState),
+}
+
+impl Input {
+    /// The recorded form of an action. Dumps and log exports only write
+    /// files, thus they are not recorded.
+    fn from_action(req: &ActionReq) -> Option<Self> {
+        let path = |path: &ObjectPath| path.as_str().to_string();
+        Some(match req {
+            ActionReq::Breakpoint((p, key, value)) => Self::Breakpoint {
+                path: path(p),
+                key: key.clone(),
+                value: value.clone(),
+            },
+            ActionReq::Trace((p, key)) => Self::Trace {
+                path: path(p),
+                key: key.clone(),
+            },
+            ActionReq::TraceVs(((xp, xk), (yp, yk))) => Self::TraceVs {
+                x: (path(xp), xk.clone()),
+                y: (path(yp), yk.clone()),
+            },
+            ActionReq::TraceSequence((p, key)) => Self::TraceSequence {
+                path: path(p),
+                key: key.clone(),
+            },
+            ActionReq::TraceAcross(((p, key), combine)) => Self::TraceAcross {
+                path: path(p),
+                key: key.clone(),
+                combine: *combine,
+            },
+            ActionReq::Watch((p, key)) => Self::Watch {
+                path: path(p),
+                key: key.clone(),
+            },
+            ActionReq::SetProp((p, key, value)) => Self::SetProp {
+                path: path(p),
+                key: key.clone(),
+                value: value.clone(),
+            },
+            ActionReq::OpenInspector(p) => Self::OpenInspector { path: path(p) },
+            ActionReq::DumpState(_) | ActionReq::ExportLogs(_) => return None,
+        })
+    }
+
+    /// The action to replay, or the run state if this is a run control.
+    fn into_action(self) -> Result<ActionReq, RunState> {
+        let path = |path: String| ObjectPath::from(path);
+        Ok(match self {
+            Self::Breakpoint {
+                path: p,
+                key,
+                value,
+            } => ActionReq::Breakpoint((path(p), key, value)),
+            Self::Trace { path: p, key } => ActionReq::Trace((path(p), key)),
+            Self::TraceVs { x, y } => ActionReq::TraceVs(((path(x.0), x.1), (path(y.0), y.1))),
+            Self::TraceSequence { path: p, key } => ActionReq::TraceSequence((path(p), key)),
+            Self::TraceAcross {
+                path: p,
+                key,
+                combine,
+            } => ActionReq::TraceAcross(((path(p), key), combine)),
+            Self::Watch { path: p, key } => ActionReq::Watch((path(p), key)),
+            Self::SetProp {
+                path: p,
+                key,
+                value,
+            } => ActionReq::SetProp((path(p), key, value)),
+            Self::OpenInspector { path: p } => ActionReq::OpenInspector(path(p)),
+            Self::Run(run) => return Err(run),
+        })
+    }
+}
+
+/// The inputs of a session and the number of dispatched events at which
+/// each was made.
+///
+/// Replays start a fresh run with the recorded seed, so the sim dispatches
+/// the same events. Inputs made between two events are replayed at once.
+/// Since a rewind would reorder the inputs, it stops the recording.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Recording {
+    pub seed: u64,
+    /// The inspectors, breakpoints and traces when the recording started.
+    #[serde(default)]
+    pub session: GuiConfig,
+    pub inputs: Vec<(usize, Input)>,
+}
+
+impl Recording {
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let f = BufWriter::new(File::create(path)?);
+        serde_norway::to_writer(f, self).map_err(io::Error::other)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let f = BufReader::new(File::open(path)?);
+        serde_norway::from_reader(f).map_err(io::Error::other)
+    }
+}
+
+/// Records the inputs of the session, or replays those of a recording.
+pub struct Recorder {
+    recording: Option<Recording>,
+    // The replayed inputs not yet due
+    pending: VecDeque<(usize, Input)>,
+    save_dialog: FileDialog,
+    load_dialog: FileDialog,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self {
+            recording: None,
+            pending: VecDeque::new(),
+            save_dialog: FileDialog::new(),
+            load_dialog: FileDialog::new(),
+        }
+    }
+
+    pub fn record(&mut self, events: usize, input: Input) {
+        if let Some(recording) = &mut self.recording {
+            recording.inputs.push((events, input));
+        }
+    }
+
+    pub fn record_action(&mut self, events: usize, req: &ActionReq) {
+        if self.recording.is_some()
+            && let Some(input) = Input::from_action(req)
+        {
+            self.record(events, input);
+        }
+    }
+
+    /// Whether a replayed input is due once `events` were dispatched.
+    pub fn is_due(&self, events: usize) -> bool {
+        self.pending.front().is_some_and(|(e, _)| *e <= events)
+    }
+
+    /// Removes all replayed inputs due once `events` were dispatched.
+    fn take_due(&mut self, events: usize) -> Vec<Input> {
+        let mut due = Vec::new();
+        while self.is_due(events) {
+            let (_, input) = self.pending.pop_front().expect("an input is due");
+            due.push(input);
+        }
+        due
+    }
+
+    /// Stops recording or replaying, e.g. since the sim was rewound.
+    pub fn stop(&mut self) {
+        if self.recording.take().is_some() {
+            ::tracing::warn!("recording stopped, since the sim was rewound");
+        }
+        if !self.pending.is_empty() {
+            self.pending.clear();
+            ::tracing::warn!("replay stopped, since the sim was rewound");
+        }
+    }
+}
+
+impl Application {
+    /// Injects the replayed inputs due at the current event.
+    pub fn replay_due_inputs(&mut self) {
+        for input in self.recorder.take_due(self.rt.events()) {
+            match input.into_action() {
+                Ok(action) => self.tx_rx.0.send(action).expect("failed to send"),
+                Err(run) => self.set_run(run),
+            }
+        }
+    }
+
+    /// Restarts the sim, so that a replay dispatches the same events, and
+    /// records all inputs from then on.
+    fn start_recording(&mut self) {
+        self.restart_with_seed(self.seed);
+        self.recorder.pending.clear();
+        let session = GuiConfig {
+            traces: self.traces.iter().flatten().map(|t| t.config()).collect(),
+            ..self.session()
+        };
+        self.recorder.recording = Some(Recording {
+            seed: self.seed,
+            session,
+            inputs: Vec::new(),
+        });
+    }
+
+    /// Restarts the sim with the seed of the recording and replaces the
+    /// inspectors, breakpoints and traces by those recorded.
+    fn start_replay(&mut self, mut recording: Recording) {
+        self.recorder.recording = None;
+        self.restart_with_seed(recording.seed);
+
+        self.modals.clear();
+        self.breakpoints.clear();
+        self.hit = None;
+        self.traces = vec![Vec::new()];
+        for t in std::mem::take(&mut recording.session.traces) {
+            let tracer = self.tracer_from_config(t);
+            self.traces[0].push(tracer);
+        }
+        self.restore_session(recording.session);
+
+        self.recorder.pending = recording.inputs.into();
+    }
+
+    pub fn render_recorder_menu(&mut self, ui: &mut Ui) {
+        ui.menu_button("Record", |ui| {
+            if let Some(recording) = &self.recorder.recording {
+                ui.label(format!("{} inputs recorded", recording.inputs.len()));
+                if ui.button("Stop and save…").clicked() {
+                    self.recorder.save_dialog = FileDialog::new()
+                        .initial_directory(self.dir.clone())
+                        .default_file_name("recording.yaml");
+                    self.recorder.save_dialog.save_file();
+                    ui.close_menu();
+                }
+                if ui.button("Discard").clicked() {
+                    self.recorder.recording = None;
+                    ui.close_menu();
+                }
+            } else if ui
+                .button("Start recording")
+                .on_hover_text("Restarts the sim and records all inputs with their event")
+                .clicked()
+            {
+                self.start_recording();
+                ui.close_menu();
+            }
+
+            ui.separator();
+            if !self.recorder.pending.is_empty() {
+                ui.label(format!(
+                    "Replaying, {} inputs left",
+                    self.recorder.pending.len()
+                ));
+                if ui.button("Cancel replay").clicked() {
+                    self.recorder.pending.clear();
+                    ui.close_menu();
+                }
+            } else if ui
+                .button("Replay…")
+                .on_hover_text("Restarts the sim with the seed of a recording and replays it")
+                .clicked()
+            {
+                self.recorder.load_dialog = FileDialog::new().initial_directory(self.dir.clone());
+                self.recorder.load_dialog.pick_file();
+                ui.close_menu();
+            }
+        });
+    }
+
+    /// Saves or loads a recording once a file was picked.
+    pub fn update_recorder(&mut self, ctx: &Context) {
+        self.recorder.save_dialog.update(ctx);
+        if let Some(path) = self.recorder.save_dialog.take_picked()
+            && let Some(recording) = self.recorder.recording.take()
+            && let Err(e) = recording.save(&path)
+        {
+            ::tracing::error!("failed to save recording to {}: {e}", path.display());
+        }
+
+        self.recorder.load_dialog.update(ctx);
+        if let Some(path) = self.recorder.load_dialog.take_picked() {
+            match Recording::load(&path) {
+                Ok(recording) => self.start_replay(recording),
+                Err(e) => {
+                    ::tracing::error!("failed to load recording {}: {e}", path.display());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_due_inputs_in_order() {
+        let mut recorder = Recorder::new();
+        recorder.recording = Some(Recording::default());
+        recorder.record_action(
+            0,
+            &ActionReq::Trace((ObjectPath::from("ping"), "counter".to_string())),
+        );
+        recorder.record(3, Input::Run(RunState::Stepping(1)));
+        recorder.record(3, Input::Run(RunState::Paused));
+
+        let recording = recorder.recording.take().unwrap();
+        recorder.pending = recording.inputs.into();
+
+        assert!(recorder.is_due(0));
+        let due = recorder.take_due(2);
+        assert!(matches!(
+            due[..],
+            [Input::Trace { ref path, .. }] if path == "ping"
+        ));
+        assert_eq!(
+            recorder.take_due(5),
+            [
+                Input::Run(RunState::Stepping(1)),
+                Input::Run(RunState::Paused)
+            ]
+        );
+        assert!(!recorder.is_due(5));
+    }
+}
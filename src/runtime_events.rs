@@ -0,0 +1,72 @@
+use des::net::ObjectPath;
+use egui::{Context, Grid, Label, RichText, ScrollArea, Sense, Window};
+use fxhash::FxHashMap;
+use tracing::Level;
+
+use crate::{Application, tracing::Event};
+
+/// Events emitted by `des` itself, e.g. for dropped messages, rather than by
+/// user modules. Only events captured within a module context are known.
+pub fn is_runtime_event(event: &Event) -> bool {
+    let target = event.metadata.target();
+    (target == "des" || target.starts_with("des::")) && *event.metadata.level() <= Level::WARN
+}
+
+impl Application {
+    pub fn render_runtime_events(&mut self, ctx: &Context) {
+        let events = self.logs.filtered_events(is_runtime_event);
+
+        let mut counts = FxHashMap::<(Level, &str), usize>::default();
+        for (_, event) in &events {
+            *counts
+                .entry((*event.metadata.level(), event.metadata.target()))
+                .or_default() += 1;
+        }
+        let mut counts = counts.into_iter().collect::<Vec<_>>();
+        counts.sort_by(|l, r| r.1.cmp(&l.1));
+
+        let colors = self.logs.colors();
+        let mut jump = None::<(ObjectPath, String)>;
+        let mut open = self.show_runtime_events;
+        Window::new("Runtime Events")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                Grid::new("runtime-event-counts")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for ((level, target), count) in &counts {
+                            ui.label(RichText::new(level.as_str()).color(colors.get(*level)));
+                            ui.label(*target);
+                            ui.label(count.to_string());
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+
+                ScrollArea::vertical().show(ui, |ui| {
+                    for (path, event) in events.iter().rev() {
+                        let text =
+                            RichText::new(format!("[{}] {} {}", event.time, path, event.fields))
+                                .color(colors.get(*event.metadata.level()));
+
+                        if ui
+                            .add(Label::new(text).sense(Sense::click()))
+                            .on_hover_text("Open the module inspector")
+                            .clicked()
+                        {
+                            jump = Some((path.clone(), event.metadata.target().to_string()));
+                        }
+                    }
+                });
+            });
+        self.show_runtime_events = open;
+
+        if let Some((path, target)) = jump {
+            self.open_inspector(path.clone());
+            if let Some(modal) = self.modals.iter_mut().find(|m| m.path == path) {
+                modal.highlight = Some(target);
+            }
+        }
+    }
+}
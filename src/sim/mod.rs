@@ -9,7 +9,22 @@ use des::{
 };
 use tracing::info_span;
 
+/// A named simulation, built from a seed.
+pub type Scenario = fn(u64) -> Runtime<Sim<()>>;
+
+/// The scenarios selectable at launch, the first being the default.
+pub const SCENARIOS: &[(&str, Scenario)] = &[("ping-pong", sim_seeded)];
+
+/// The scenario registered as `name`.
+pub fn scenario(name: &str) -> Option<Scenario> {
+    SCENARIOS.iter().find(|(n, _)| *n == name).map(|(_, f)| *f)
+}
+
 pub fn sim() -> Runtime<Sim<()>> {
+    sim_seeded(123)
+}
+
+pub fn sim_seeded(seed: u64) -> Runtime<Sim<()>> {
     let mut sim = Sim::new(());
     sim.node(
         "ping",
@@ -64,7 +79,7 @@ pub fn sim() -> Runtime<Sim<()>> {
 
     let gate = sim.gate("ping", "port");
 
-    let mut rt = Builder::seeded(123).build(sim.freeze());
+    let mut rt = Builder::seeded(seed).build(sim.freeze());
     for i in 0..100 {
         rt.add_message_onto(
             gate.clone(),
@@ -75,3 +90,14 @@ pub fn sim() -> Runtime<Sim<()>> {
 
     rt
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_scenarios_by_name() {
+        assert!(scenario("ping-pong").is_some());
+        assert!(scenario("missing").is_none());
+    }
+}
@@ -0,0 +1,136 @@
+use des::time::SimTime;
+use egui::{Color32, Context, Slider, Stroke, TopBottomPanel, pos2};
+
+use crate::{Application, RunState};
+
+/// Known `(time, events dispatched)` pairs, recorded once per frame.
+///
+/// Since runtimes cannot be cloned, these act as snapshots: jumping back
+/// replays the runtime up to the nearest checkpoint before the target.
+#[derive(Debug, Default)]
+pub struct Timeline {
+    checkpoints: Vec<(SimTime, usize)>,
+    scrub: Option<f64>,
+}
+
+impl Timeline {
+    pub fn record(&mut self, time: SimTime, events: usize) {
+        // After a jump back, the replayed run re-records known checkpoints
+        if self.checkpoints.last().is_none_or(|(_, e)| *e < events) {
+            self.checkpoints.push((time, events));
+        }
+    }
+
+    fn end(&self) -> f64 {
+        self.checkpoints
+            .last()
+            .map_or(0.0, |(time, _)| time.as_secs_f64())
+    }
+
+    /// The latest checkpoint at or before `time`.
+    fn before(&self, time: SimTime) -> (SimTime, usize) {
+        let idx = self.checkpoints.partition_point(|(t, _)| *t <= time);
+        idx.checked_sub(1)
+            .map_or((SimTime::ZERO, 0), |idx| self.checkpoints[idx])
+    }
+
+    /// The latest checkpoint strictly before `events`.
+    pub fn latest_before(&self, events: usize) -> Option<(SimTime, usize)> {
+        let idx = self.checkpoints.partition_point(|(_, e)| *e < events);
+        idx.checked_sub(1).map(|idx| self.checkpoints[idx])
+    }
+}
+
+impl Application {
+    pub fn render_timeline(&mut self, ctx: &Context) {
+        let now = self.rt.time().as_secs_f64();
+
+        let mut target = None;
+        TopBottomPanel::bottom("timeline").show(ctx, |ui| {
+            let end = self.timeline.end().max(now);
+            let mut value = self.timeline.scrub.unwrap_or(now);
+
+            ui.spacing_mut().slider_width = ui.available_width() - 120.0;
+            let resp = ui.add(
+                Slider::new(&mut value, 0.0..=end)
+                    .show_value(true)
+                    .suffix("s"),
+            );
+
+            if resp.dragged() || resp.changed() {
+                self.timeline.scrub = Some(value);
+            }
+            if (resp.drag_stopped() || (resp.changed() && !resp.dragged()))
+                && let Some(value) = self.timeline.scrub.take()
+            {
+                target = Some(SimTime::from(value));
+            }
+
+            // Breakpoint triggers as ticks on the slider track
+            let rect = resp.rect;
+            let width = ui.spacing().slider_width;
+            for b in &self.breakpoints {
+                for (time, _) in &b.history {
+                    let x =
+                        rect.left() + (time.as_secs_f64() / end.max(f64::EPSILON)) as f32 * width;
+                    ui.painter().line_segment(
+                        [pos2(x, rect.top()), pos2(x, rect.bottom())],
+                        Stroke::new(1.0, Color32::RED),
+                    );
+                }
+            }
+        });
+
+        if let Some(target) = target {
+            self.jump_to(target);
+        }
+    }
+
+    /// Moves the sim to `target`, replaying from the nearest earlier
+    /// checkpoint if it lies in the past, then running forward.
+    pub fn jump_to(&mut self, target: SimTime) {
+        if target < self.rt.time() {
+            let (_, events) = self.timeline.before(target);
+            self.replay_to(events);
+        }
+        self.param.until = Some(target);
+        self.set_run(RunState::Running);
+    }
+
+    /// Rewinds to the latest checkpoint before the current event.
+    pub fn rewind(&mut self, events: usize) {
+        if let Some((_, events)) = self.timeline.latest_before(events) {
+            self.replay_to(events);
+        }
+    }
+
+    /// Undoes the most recent event.
+    ///
+    /// There is no buffer of past states, since `Sim<()>` cannot be cloned.
+    /// Instead the runtime is replayed from the start up to `events - 1`, so
+    /// a step back costs no memory, but time linear in the dispatched events.
+    pub fn step_back(&mut self, events: usize) {
+        if let Some(target) = events.checked_sub(1) {
+            self.replay_to(target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latest_before_skips_the_current_event() {
+        let mut timeline = Timeline::default();
+        for events in [10, 20, 30] {
+            timeline.record(SimTime::from(events as f64), events);
+        }
+        // A replay re-records known checkpoints
+        timeline.record(SimTime::from(10.0), 10);
+
+        assert_eq!(timeline.latest_before(30), Some((SimTime::from(20.0), 20)));
+        assert_eq!(timeline.latest_before(25), Some((SimTime::from(20.0), 20)));
+        assert_eq!(timeline.latest_before(10), None);
+    }
+}
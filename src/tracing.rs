@@ -1,15 +1,26 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    cmp::Ordering,
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
 use des::{
     net::{ObjectPath, module::try_current},
     time::SimTime,
 };
-use egui::ahash::HashMap;
+use egui::{Color32, ahash::HashMap};
+use regex::Regex;
 use serde::{
     Deserialize, Serialize,
     ser::{SerializeMap, SerializeStruct},
 };
-use tracing::{Metadata, Subscriber};
+use tracing::{
+    Level, Metadata, Subscriber,
+    field::{Field, Visit},
+};
 use tracing_subscriber::{
     fmt::{FormatEvent, FormatFields, FormattedFields, format::Writer},
     registry::LookupSpan,
@@ -22,6 +33,8 @@ pub struct Event {
     pub module: ObjectPath,
     pub span: String,
     pub fields: String,
+    /// The fields other than the message, by name, in recording order.
+    pub values: Vec<(&'static str, String)>,
 }
 
 impl Serialize for Event {
@@ -61,17 +74,303 @@ pub struct Span {
     pub fields: String,
 }
 
+impl Span {
+    fn parse(s: &str) -> Self {
+        match s.split_once('{') {
+            Some((name, fields)) => Self {
+                name: name.to_string(),
+                fields: fields.strip_suffix('}').unwrap_or(fields).to_string(),
+            },
+            None => Self {
+                name: s.to_string(),
+                fields: String::new(),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.fields.is_empty() {
+            true => write!(f, "{}", self.name),
+            false => write!(f, "{}{{{}}}", self.name, self.fields),
+        }
+    }
+}
+
 impl Event {
+    /// The value of a field, as recorded in `values`.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| value.as_str())
+    }
+
     pub fn matches(&self, query: &str) -> bool {
         self.fields.contains(query)
             | self.span.contains(query)
             | self.module.as_str().contains(query)
     }
+
+    /// The spans the event was emitted in, from the root to the innermost.
+    ///
+    /// The span is captured as `name{fields}:name{fields}`, colons within
+    /// the braces of fields are kept.
+    pub fn spans(&self) -> Vec<Span> {
+        let mut spans = Vec::new();
+        let mut depth = 0usize;
+        let mut start = 0;
+        for (i, c) in self.span.char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth = depth.saturating_sub(1),
+                ':' if depth == 0 => {
+                    spans.push(Span::parse(&self.span[start..i]));
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        if start < self.span.len() {
+            spans.push(Span::parse(&self.span[start..]));
+        }
+        spans
+    }
+
+    /// Like [`Event::matches`], but with a regex instead of a substring.
+    pub fn matches_regex(&self, regex: &Regex) -> bool {
+        regex.is_match(&self.fields)
+            | regex.is_match(&self.span)
+            | regex.is_match(self.module.as_str())
+    }
 }
 
-#[derive(Debug, Clone, Default)]
+/// The number of events kept per module, unless configured otherwise.
+pub const DEFAULT_MAX_EVENTS: usize = 10_000;
+pub const DEFAULT_MAX_MERGED: usize = 100_000;
+
+#[derive(Debug, Clone)]
 pub struct GuiTracingObserver {
     pub streams: Arc<Mutex<HashMap<ObjectPath, ModuleLog>>>,
+    /// The module that most recently emitted an event.
+    pub watermark: Arc<Mutex<Option<ObjectPath>>>,
+    /// The level colors, shared by all views of the logs.
+    pub colors: Arc<Mutex<LogColors>>,
+    /// Events at or above this level trip the log breakpoint.
+    pub break_level: Arc<Mutex<Option<Level>>>,
+    /// The first event that met `break_level`, until taken.
+    pub tripped: Arc<Mutex<Option<Event>>>,
+    /// The number of most recent events kept per module, older ones are
+    /// dropped to bound memory on long runs.
+    pub max_events: Arc<Mutex<usize>>,
+    /// The number of most recent events kept in `merged`, across all modules.
+    pub max_merged: Arc<Mutex<usize>>,
+    /// The events of all modules in capture order, which is also time
+    /// order, thus a merged view of all streams. Bound by `max_merged`.
+    pub merged: Arc<Mutex<VecDeque<Event>>>,
+}
+
+impl Default for GuiTracingObserver {
+    fn default() -> Self {
+        Self {
+            streams: Arc::default(),
+            watermark: Arc::default(),
+            colors: Arc::default(),
+            break_level: Arc::default(),
+            tripped: Arc::default(),
+            max_events: Arc::new(Mutex::new(DEFAULT_MAX_EVENTS)),
+            max_merged: Arc::new(Mutex::new(DEFAULT_MAX_MERGED)),
+            merged: Arc::default(),
+        }
+    }
+}
+
+/// The color of each log level, from `TRACE` to `ERROR`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct LogColors(pub [Color32; 5]);
+
+impl LogColors {
+    pub fn get(&self, level: Level) -> Color32 {
+        self.0[Self::index(level)]
+    }
+
+    pub fn get_mut(&mut self, level: Level) -> &mut Color32 {
+        &mut self.0[Self::index(level)]
+    }
+
+    fn index(level: Level) -> usize {
+        match level {
+            Level::TRACE => 0,
+            Level::DEBUG => 1,
+            Level::INFO => 2,
+            Level::WARN => 3,
+            Level::ERROR => 4,
+        }
+    }
+}
+
+impl Default for LogColors {
+    fn default() -> Self {
+        Self([
+            Color32::from_rgb(0, 128, 0),
+            Color32::from_rgb(0, 0, 255),
+            Color32::from_rgb(0, 255, 0),
+            Color32::from_rgb(255, 255, 0),
+            Color32::from_rgb(255, 0, 0),
+        ])
+    }
+}
+
+/// Accessors for embedders, e.g. to assert on logs in tests.
+///
+/// The observer may be shared across threads. All accessors lock the
+/// streams only for the duration of the call and return clones, so no
+/// lock is held while the caller inspects the events.
+impl GuiTracingObserver {
+    pub fn colors(&self) -> LogColors {
+        *self.colors.lock().expect("failed to lock")
+    }
+
+    pub fn set_colors(&self, colors: LogColors) {
+        *self.colors.lock().expect("failed to lock") = colors;
+    }
+
+    pub fn set_break_level(&self, level: Option<Level>) {
+        *self.break_level.lock().expect("failed to lock") = level;
+    }
+
+    pub fn max_events(&self) -> usize {
+        *self.max_events.lock().expect("failed to lock")
+    }
+
+    /// Sets the per-module retention, applied to the captured events
+    /// immediately.
+    pub fn set_max_events(&self, max_events: usize) {
+        *self.max_events.lock().expect("failed to lock") = max_events;
+        let mut streams = self.streams.lock().expect("failed to lock");
+        for log in streams.values_mut() {
+            log.retain_last(max_events);
+        }
+    }
+
+    pub fn max_merged(&self) -> usize {
+        *self.max_merged.lock().expect("failed to lock")
+    }
+
+    /// Sets the retention of the merged view, applied immediately.
+    pub fn set_max_merged(&self, max_merged: usize) {
+        *self.max_merged.lock().expect("failed to lock") = max_merged;
+        let mut merged = self.merged.lock().expect("failed to lock");
+        let excess = merged.len().saturating_sub(max_merged);
+        merged.drain(..excess);
+    }
+
+    /// Takes the first event that met the break level since the last call.
+    pub fn take_tripped(&self) -> Option<Event> {
+        self.tripped.lock().expect("failed to lock").take()
+    }
+
+    /// All events captured for the given module, in capture order.
+    pub fn events_for(&self, path: &ObjectPath) -> Vec<Event> {
+        let streams = self.streams.lock().expect("failed to lock");
+        streams
+            .get(path)
+            .map(|log| log.output().to_vec())
+            .unwrap_or_default()
+    }
+
+    /// All captured events of all modules, sorted by time.
+    pub fn all_events(&self) -> Vec<(ObjectPath, Event)> {
+        self.filtered_events(|_| true)
+    }
+
+    /// All captured events matching `query` (see [`Event::matches`]), sorted by time.
+    pub fn matching_events(&self, query: &str) -> Vec<(ObjectPath, Event)> {
+        self.filtered_events(|event| event.matches(query))
+    }
+
+    /// All captured events for which `f` holds, sorted by time.
+    pub fn filtered_events(&self, f: impl Fn(&Event) -> bool) -> Vec<(ObjectPath, Event)> {
+        let streams = self.streams.lock().expect("failed to lock");
+        let mut events = streams
+            .iter()
+            .flat_map(|(path, log)| {
+                log.output()
+                    .iter()
+                    .filter(|event| f(event))
+                    .map(|event| (path.clone(), event.clone()))
+            })
+            .collect::<Vec<_>>();
+        drop(streams);
+
+        // stable, so events of one module keep their capture order
+        events.sort_by(|l, r| l.1.time.partial_cmp(&r.1.time).unwrap_or(Ordering::Equal));
+        events
+    }
+
+    /// Takes the module that most recently emitted an event, used to
+    /// attribute a dispatched event to its handling module.
+    pub fn take_watermark(&self) -> Option<ObjectPath> {
+        self.watermark.lock().expect("failed to lock").take()
+    }
+
+    /// Writes all events of a module to `<path>.logs.yaml` in `dir`.
+    pub fn export(&self, path: &ObjectPath, dir: &Path) -> io::Result<()> {
+        let events = {
+            let lock = self.streams.lock().expect("failed to lock");
+            lock.get(path).map(ModuleLog::output).unwrap_or_default()
+        };
+        let f = File::create(dir.join(format!("{path}.logs.yaml")))?;
+        serde_norway::to_writer(BufWriter::new(f), &events).map_err(io::Error::other)
+    }
+
+    /// Writes the events of all modules to `path`, sorted by time.
+    pub fn save(&self, path: &Path, format: LogFormat) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        for (_, event) in self.all_events() {
+            match format {
+                LogFormat::Json => serde_json::to_writer(&mut w, &event)?,
+                LogFormat::Text => write!(
+                    w,
+                    "[{}] {} {} {}: {} {}",
+                    event.time,
+                    event.metadata.level(),
+                    event.module,
+                    event.metadata.target(),
+                    event.span,
+                    event.fields
+                )?,
+            }
+            writeln!(w)?;
+        }
+        w.flush()
+    }
+
+    /// Drops all captured events.
+    pub fn clear(&self) {
+        self.streams.lock().expect("failed to lock").clear();
+        self.merged.lock().expect("failed to lock").clear();
+    }
+}
+
+/// Captures the fields of an event one by one, unlike the formatted
+/// `fields`. The message is skipped, since it is not a structured field.
+#[derive(Default)]
+struct FieldVisitor(Vec<(&'static str, String)>);
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() != "message" {
+            self.0.push((field.name(), value.to_string()));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() != "message" {
+            self.0.push((field.name(), format!("{value:?}")));
+        }
+    }
 }
 
 impl<S, N> FormatEvent<S, N> for GuiTracingObserver
@@ -85,12 +384,16 @@ where
         mut writer: Writer<'_>,
         event: &tracing::Event<'_>,
     ) -> std::fmt::Result {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+
         let mut json = Event {
             time: SimTime::now(),
             metadata: event.metadata(),
             module: try_current().ok_or(std::fmt::Error)?.path(),
             span: String::new(),
             fields: String::new(),
+            values: visitor.0,
         };
 
         let mut txt_writer = Writer::new(&mut json.span);
@@ -117,13 +420,44 @@ where
         let mut buf_writer = Writer::new(&mut json.fields);
         ctx.format_fields(buf_writer.by_ref(), event)?;
 
+        *self.watermark.lock().expect("failed to lock") = Some(json.module.clone());
+
+        // Levels compare by verbosity, so more severe levels are smaller
+        let break_level = *self.break_level.lock().expect("failed to lock");
+        if break_level.is_some_and(|level| *json.metadata.level() <= level) {
+            self.tripped
+                .lock()
+                .expect("failed to lock")
+                .get_or_insert_with(|| json.clone());
+        }
+
+        let max_merged = *self.max_merged.lock().expect("failed to lock");
+        let mut merged = self.merged.lock().expect("failed to lock");
+        if merged.len() >= max_merged {
+            merged.pop_front();
+        }
+        merged.push_back(json.clone());
+        drop(merged);
+
+        let max_events = *self.max_events.lock().expect("failed to lock");
         let mut streams = self.streams.lock().expect("failed to lock");
-        streams.entry(json.module.clone()).or_default().push(json);
+        let log = streams.entry(json.module.clone()).or_default();
+        log.push(json);
+        log.retain_last(max_events);
 
         Ok(())
     }
 }
 
+/// The file format of [`GuiTracingObserver::save`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Newline-delimited JSON, one serialized [`Event`] per line.
+    Json,
+    /// One human readable line per event.
+    Text,
+}
+
 /// The totality of logs for a given module.
 ///
 /// desired output:
@@ -133,14 +467,155 @@ where
 #[derive(Debug, Default)]
 pub struct ModuleLog {
     events: Vec<Event>,
+    // Events before this index are dropped, but not yet removed
+    start: usize,
 }
 
 impl ModuleLog {
     pub fn output(&self) -> &[Event] {
-        &self.events
+        &self.events[self.start..]
     }
 
     pub fn push(&mut self, event: Event) {
         self.events.push(event.clone());
     }
+
+    /// Drops all but the `n` most recent events.
+    ///
+    /// Dropped events are removed in batches, so that the cost of shifting
+    /// the kept events is amortized over many pushes.
+    pub fn retain_last(&mut self, n: usize) {
+        self.start = self.start.max(self.events.len().saturating_sub(n));
+        if self.start > n.max(1024) {
+            self.events.drain(..self.start);
+            self.start = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::{fmt::Layer, layer::SubscriberExt};
+
+    use super::*;
+
+    #[test]
+    fn captures_events_of_sample_sim() {
+        let observer = GuiTracingObserver::default();
+        let subscriber = tracing_subscriber::Registry::default().with(
+            Layer::default()
+                .with_ansi(false)
+                .event_format(observer.clone()),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _ = crate::sim::sim().run();
+        });
+
+        let ping = observer.events_for(&ObjectPath::from("ping"));
+        assert!(ping.iter().all(|event| event.module.as_str() == "ping"));
+        let pongs = ping
+            .iter()
+            .filter(|event| event.fields.contains("PONG"))
+            .collect::<Vec<_>>();
+        assert_eq!(pongs.len(), 100);
+        assert!(pongs[0].span.contains("pinger"));
+        assert!(pongs.iter().all(|event| event.field("state").is_some()));
+        assert_eq!(pongs[0].field("message"), None);
+
+        let pong = observer.events_for(&ObjectPath::from("pong"));
+        assert_eq!(
+            pong.iter()
+                .filter(|event| event.fields.contains("PING"))
+                .count(),
+            100
+        );
+
+        let all = observer.all_events();
+        assert_eq!(all.len(), ping.len() + pong.len());
+        assert!(all.windows(2).all(|w| w[0].1.time <= w[1].1.time));
+
+        assert_eq!(observer.matching_events("PONG").len(), 100);
+    }
+
+    #[test]
+    fn parses_span_hierarchy() {
+        let observer = GuiTracingObserver::default();
+        let subscriber = tracing_subscriber::Registry::default().with(
+            Layer::default()
+                .with_ansi(false)
+                .event_format(observer.clone()),
+        );
+        tracing::subscriber::with_default(subscriber, || {
+            let _ = crate::sim::sim().run();
+        });
+
+        let ping = observer.events_for(&ObjectPath::from("ping"));
+        let spans = ping[0].spans();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].name, "pinger");
+        assert_eq!(spans[0].fields, "state=1");
+        assert_eq!(spans[0].to_string(), ping[0].span);
+
+        let mut event = ping[0].clone();
+        event.span = "outer{addr=1:2}:inner".to_string();
+        let spans = event.spans();
+        assert_eq!(spans[0].fields, "addr=1:2");
+        assert_eq!(spans[1].name, "inner");
+    }
+
+    #[test]
+    fn trips_on_break_level() {
+        let observer = GuiTracingObserver::default();
+        let run = || {
+            let subscriber = tracing_subscriber::Registry::default().with(
+                Layer::default()
+                    .with_ansi(false)
+                    .event_format(observer.clone()),
+            );
+            tracing::subscriber::with_default(subscriber, || {
+                let _ = crate::sim::sim().run();
+            });
+        };
+
+        observer.set_break_level(Some(Level::WARN));
+        run();
+        assert!(observer.take_tripped().is_none());
+
+        observer.set_break_level(Some(Level::DEBUG));
+        run();
+        let tripped = observer.take_tripped().expect("an INFO event should trip");
+        assert_eq!(*tripped.metadata.level(), Level::INFO);
+        assert!(observer.take_tripped().is_none());
+    }
+
+    #[test]
+    fn retains_most_recent_events() {
+        let observer = GuiTracingObserver::default();
+        observer.set_max_events(10);
+        let subscriber = tracing_subscriber::Registry::default().with(
+            Layer::default()
+                .with_ansi(false)
+                .event_format(observer.clone()),
+        );
+        tracing::subscriber::with_default(subscriber, || {
+            let _ = crate::sim::sim().run();
+        });
+
+        let pong = observer.events_for(&ObjectPath::from("pong"));
+        assert_eq!(pong.len(), 10);
+        assert!(pong.windows(2).all(|w| w[0].time <= w[1].time));
+
+        observer.set_max_events(3);
+        assert_eq!(observer.events_for(&ObjectPath::from("pong")).len(), 3);
+        // The merged view has a retention of its own
+        let merged = observer.merged.lock().unwrap().len();
+        assert!(merged > 3);
+
+        observer.set_max_merged(3);
+        let mut merged = observer.merged.lock().unwrap();
+        assert_eq!(merged.len(), 3);
+        let merged = merged.make_contiguous();
+        assert!(merged.windows(2).all(|w| w[0].time <= w[1].time));
+    }
 }
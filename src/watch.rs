@@ -0,0 +1,109 @@
+use des::{net::ObjectPath, time::SimTime};
+use egui::{Context, TextStyle, Window};
+use egui_extras::{Column, TableBuilder};
+use fxhash::FxHashMap;
+use serde_norway::Value;
+
+use crate::{Application, inspector::copy_text, plot::access};
+
+/// The change history of a single property, for values that cannot be
+/// plotted, e.g. strings or bools.
+///
+/// Observed values are checked after every event, so no change is missed.
+#[derive(Debug)]
+pub struct WatchTable {
+    pub path: ObjectPath,
+    pub key: String,
+    rows: Vec<(SimTime, Value)>,
+    pub remove: bool,
+}
+
+impl WatchTable {
+    pub fn new(path: ObjectPath, key: String) -> Self {
+        Self {
+            path,
+            key,
+            rows: Vec::new(),
+            remove: false,
+        }
+    }
+
+    /// Appends a row, if the value differs from the latest one.
+    pub fn update(&mut self, observers: &FxHashMap<ObjectPath, Value>) {
+        let Some(value) = observers
+            .get(&self.path)
+            .and_then(|value| access(value, &self.key))
+        else {
+            return;
+        };
+        if self.rows.last().is_none_or(|(_, last)| *last != value) {
+            self.rows.push((SimTime::now(), value));
+        }
+    }
+
+    /// Drops all rows recorded after `time`.
+    pub fn truncate(&mut self, time: SimTime) {
+        self.rows.retain(|(t, _)| *t <= time);
+    }
+}
+
+impl Application {
+    pub fn render_watches(&mut self, ctx: &Context) {
+        for watch in &mut self.watches {
+            let mut open = true;
+            Window::new(format!("Watch {} {}", watch.path, watch.key))
+                .open(&mut open)
+                .default_width(300.0)
+                .show(ctx, |ui| {
+                    let row_height = ui.text_style_height(&TextStyle::Body);
+                    TableBuilder::new(ui)
+                        .striped(true)
+                        .column(Column::initial(100.0).resizable(true))
+                        .column(Column::remainder())
+                        .stick_to_bottom(true)
+                        .header(row_height, |mut header| {
+                            header.col(|ui| {
+                                ui.strong("time");
+                            });
+                            header.col(|ui| {
+                                ui.strong("value");
+                            });
+                        })
+                        .body(|body| {
+                            body.rows(row_height, watch.rows.len(), |mut row| {
+                                let (time, value) = &watch.rows[row.index()];
+                                row.col(|ui| {
+                                    ui.label(format!("{time:?}"));
+                                });
+                                row.col(|ui| {
+                                    ui.label(copy_text(value));
+                                });
+                            });
+                        });
+                });
+            watch.remove = !open;
+        }
+        self.watches.retain(|w| !w.remove);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plot::observers;
+
+    #[test]
+    fn records_changes_only() {
+        let path = ObjectPath::from("ping");
+        let mut watch = WatchTable::new(path.clone(), "state".to_string());
+        for state in ["idle", "idle", "busy"] {
+            watch.update(&observers(&path, "state", state));
+        }
+        let values = watch
+            .rows
+            .iter()
+            .map(|(_, v)| copy_text(v))
+            .collect::<Vec<_>>();
+        assert_eq!(values, ["idle", "busy"]);
+    }
+}